@@ -0,0 +1,138 @@
+//! Fixed-capacity set of 7-bit I2C addresses.
+
+/// A set of 7-bit I2C addresses, stored as a 128-bit bitmap so it never needs to allocate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AddressSet(u128);
+
+impl AddressSet {
+    /// Create an empty address set.
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Insert an address into the set. Returns `false` if the address is not a valid 7-bit
+    /// address, in which case the set is left unchanged.
+    pub fn insert(&mut self, addr: u8) -> bool {
+        if addr > 0x7F {
+            return false;
+        }
+
+        self.0 |= 1 << addr;
+        true
+    }
+
+    /// Remove an address from the set.
+    pub fn remove(&mut self, addr: u8) {
+        if addr <= 0x7F {
+            self.0 &= !(1 << addr);
+        }
+    }
+
+    /// Check if the set contains an address.
+    pub fn contains(&self, addr: u8) -> bool {
+        addr <= 0x7F && self.0 & (1 << addr) != 0
+    }
+
+    /// Number of addresses in the set.
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Check if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterate over the addresses in the set in ascending order.
+    pub fn iter(&self) -> Iter {
+        Iter(self.0)
+    }
+}
+
+impl IntoIterator for AddressSet {
+    type Item = u8;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Iter {
+        self.iter()
+    }
+}
+
+/// Iterator over the addresses in an [`AddressSet`].
+#[derive(Clone, Debug)]
+pub struct Iter(u128);
+
+impl Iterator for Iter {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.0 == 0 {
+            return None;
+        }
+
+        let addr = self.0.trailing_zeros() as u8;
+        self.0 &= self.0 - 1;
+        Some(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AddressSet;
+
+    #[test]
+    fn rejects_invalid_addrs() {
+        let mut set = AddressSet::new();
+        assert!(!set.insert(0x80));
+        assert!(!set.insert(0xFF));
+        assert!(set.is_empty());
+        assert!(!set.contains(0x80));
+
+        // Removing an invalid address must not touch valid ones
+        assert!(set.insert(0x00));
+        set.remove(0x80);
+        set.remove(0xFF);
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(0x00));
+    }
+
+    #[test]
+    fn insert_contains_remove() {
+        let mut set = AddressSet::new();
+        assert!(set.insert(0x50));
+        assert!(set.insert(0x50));
+        assert!(set.contains(0x50));
+        assert!(!set.contains(0x51));
+        assert_eq!(set.len(), 1);
+
+        set.remove(0x50);
+        assert!(!set.contains(0x50));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn iter_ascending_including_bounds() {
+        let mut set = AddressSet::new();
+        for addr in [0x7F, 0x40, 0x00, 0x08, 0x77] {
+            set.insert(addr);
+        }
+
+        let mut iter = set.iter();
+        for expected in [0x00, 0x08, 0x40, 0x77, 0x7F] {
+            assert_eq!(iter.next(), Some(expected));
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(set.into_iter().count(), 5);
+    }
+
+    #[test]
+    fn iter_full_set() {
+        let mut set = AddressSet::new();
+        for addr in 0..=0x7F {
+            assert!(set.insert(addr));
+        }
+        assert_eq!(set.len(), 128);
+        assert!(set.iter().eq(0..=0x7F));
+    }
+}
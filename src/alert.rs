@@ -0,0 +1,164 @@
+//! SMBus Alert Response Address handling.
+//!
+//! When a device asserts SMBALERT#, the host reads a byte from the Alert Response Address. Every
+//! alerting device takes part, and the one with the lowest address wins arbitration, returns its
+//! address and releases SMBALERT#. Reading repeatedly therefore drains all pending alerts.
+
+use embedded_hal::i2c;
+
+use crate::Error;
+
+pub use blocking_alerts::Alerts;
+
+/// SMBus Alert Response Address.
+pub const ALERT_RESPONSE_ADDR: u8 = 0x0C;
+
+// Upper bound on alerts drained at once (one per 7-bit address), so a device that never releases
+// SMBALERT# cannot cause an endless loop
+const MAX_ALERTS: usize = 128;
+
+// The alerting device returns its address in the upper 7 bits, with no device meaning a NACK
+fn res_map<E: i2c::Error>(res: Result<(), E>, byte: u8) -> Result<Option<u8>, Error<E>> {
+    match res {
+        Ok(()) => Ok(Some(byte >> 1)),
+        Err(e) if matches!(e.kind(), i2c::ErrorKind::NoAcknowledge(_)) => Ok(None),
+        Err(e) => Err(Error::I2C(e)),
+    }
+}
+
+/// Error encountered while waiting for an alert.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AlertError<E, PE> {
+    /// Error reading from the Alert Response Address.
+    Bus(Error<E>),
+    /// SMBALERT# pin error.
+    Pin(PE),
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch")
+)]
+mod asynch {
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{ALERT_RESPONSE_ADDR, MAX_ALERTS, res_map};
+    use crate::Error;
+    use crate::asynch::GeneralCall;
+
+    impl<I2C: I2c, D> GeneralCall<I2C, D> {
+        /// Read from the SMBus Alert Response Address, returning the 7-bit address of the alerting
+        /// device with the lowest address, or `None` if no device is alerting.
+        ///
+        /// # Errors
+        ///
+        /// If any I2C error other than a NACK occurs, the underlying error will be returned.
+        pub async fn alert_response(&mut self) -> Result<Option<u8>, Error<I2C::Error>> {
+            let mut byte = [0];
+            let res = self.i2c.read(ALERT_RESPONSE_ADDR, &mut byte).await;
+            res_map(res, byte[0])
+        }
+
+        /// Read from the SMBus Alert Response Address until no device is alerting anymore, calling
+        /// `f` with the 7-bit address of each alerting device.
+        ///
+        /// Returns the number of alerts drained.
+        ///
+        /// # Errors
+        ///
+        /// If any I2C error other than a NACK occurs, the underlying error will be returned.
+        pub async fn drain_alerts(
+            &mut self,
+            mut f: impl FnMut(u8),
+        ) -> Result<usize, Error<I2C::Error>> {
+            let mut count = 0;
+            while count < MAX_ALERTS {
+                match self.alert_response().await? {
+                    Some(addr) => f(addr),
+                    None => break,
+                }
+                count += 1;
+            }
+
+            Ok(count)
+        }
+    }
+}
+
+mod blocking_alerts {
+    use embedded_hal::i2c::I2c;
+
+    use super::MAX_ALERTS;
+    use crate::{Error, GeneralCall};
+
+    /// Iterator draining pending SMBus alerts, see [`GeneralCall::alerts`].
+    pub struct Alerts<'a, I2C: I2c, D> {
+        general_call: &'a mut GeneralCall<I2C, D>,
+        count: usize,
+        done: bool,
+    }
+
+    impl<I2C: I2c, D> Iterator for Alerts<'_, I2C, D> {
+        type Item = Result<u8, Error<I2C::Error>>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.done || self.count >= MAX_ALERTS {
+                return None;
+            }
+
+            self.count += 1;
+            let res = self.general_call.alert_response().transpose();
+            // Stop after no device is alerting anymore or an error occurred
+            self.done = !matches!(res, Some(Ok(_)));
+            res
+        }
+    }
+
+    impl<I2C: I2c, D> GeneralCall<I2C, D> {
+        /// Iterate over the 7-bit addresses of alerting devices, reading from the SMBus Alert
+        /// Response Address until no device is alerting anymore.
+        ///
+        /// The iterator ends after the first error.
+        pub fn alerts(&mut self) -> Alerts<'_, I2C, D> {
+            Alerts {
+                general_call: self,
+                count: 0,
+                done: false,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+mod async_alerts {
+    use embedded_hal_async::digital::Wait;
+    use embedded_hal_async::i2c::I2c;
+
+    use super::AlertError;
+    use crate::asynch::GeneralCall;
+
+    impl<I2C: I2c, D> GeneralCall<I2C, D> {
+        /// Wait for the active-low SMBALERT# pin to be asserted, then read from the SMBus Alert
+        /// Response Address.
+        ///
+        /// Returns the 7-bit address of the alerting device with the lowest address, or `None`
+        /// if no device responded (for instance, if the alert was already handled).
+        ///
+        /// # Errors
+        ///
+        /// If waiting on the pin fails, [`AlertError::Pin`] will be returned.
+        ///
+        /// If any I2C error other than a NACK occurs, [`AlertError::Bus`] will be returned.
+        pub async fn wait_for_alert<P: Wait>(
+            &mut self,
+            alert: &mut P,
+        ) -> Result<Option<u8>, AlertError<I2C::Error, P::Error>> {
+            alert.wait_for_low().await.map_err(AlertError::Pin)?;
+            self.alert_response().await.map_err(AlertError::Bus)
+        }
+    }
+}
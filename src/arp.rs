@@ -0,0 +1,586 @@
+//! SMBus Address Resolution Protocol (ARP) controller.
+//!
+//! ARP dynamically assigns addresses to SMBus devices, identified by their 128-bit Unique Device
+//! Identifier (UDID). All ARP commands are sent to the SMBus Device Default Address and protected
+//! by Packet Error Checking (PEC).
+
+use embedded_hal::i2c;
+
+use crate::AddressSet;
+use crate::pec::Pec;
+use crate::reserved::{is_target_addr, target_addrs};
+
+pub use blocking::ArpController;
+
+/// SMBus Device Default Address, which ARP-capable devices respond to.
+pub const DEVICE_DEFAULT_ADDR: u8 = 0x61;
+
+/// Addresses reserved by SMBus and PMBus, which are never assigned by default.
+pub const SMBUS_RESERVED_ADDRS: [u8; 5] = [
+    0x08, // SMBus Host
+    0x0C, // SMBus Alert Response
+    0x28, // PMBus Zone Read
+    0x37, // PMBus Zone Write
+    DEVICE_DEFAULT_ADDR,
+];
+
+// General ARP commands
+const PREPARE_TO_ARP: u8 = 0x01;
+const RESET_DEVICE: u8 = 0x02;
+const GET_UDID: u8 = 0x03;
+const ASSIGN_ADDRESS: u8 = 0x04;
+
+// Byte count of the Get UDID and Assign Address blocks (UDID followed by an address)
+const UDID_BLOCK_LEN: u8 = 17;
+
+// Address byte reported by Get UDID when the device has no valid address
+const NO_ADDR: u8 = 0xFF;
+
+/// ARP error.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ArpError<E> {
+    /// No device acknowledged the ARP command.
+    NoAck,
+    /// The PEC byte received from a device did not match the data.
+    Pec,
+    /// A device returned an unexpected block byte count.
+    ByteCount(u8),
+    /// No address is left in the pool to assign to a device.
+    PoolExhausted,
+    /// More devices responded than fit in the provided device list.
+    TooManyDevices,
+    /// The provided address is not a valid 7-bit target address, or cannot be used with the
+    /// command.
+    InvalidAddr,
+    /// Other I2C error was encountered.
+    I2C(E),
+}
+
+fn res_map<E: i2c::Error>(res: Result<(), E>) -> Result<(), ArpError<E>> {
+    res.map_err(|e| match e.kind() {
+        i2c::ErrorKind::NoAcknowledge(_) => ArpError::NoAck,
+        _ => ArpError::I2C(e),
+    })
+}
+
+// Directed ARP commands encode the target address, with the LSB selecting the command. Addresses
+// below 0x10 would encode a general or reserved command instead, so they are rejected too.
+fn directed_cmd<E>(addr: u8, get_udid: bool) -> Result<u8, ArpError<E>> {
+    if addr < 0x10 || !is_target_addr(addr) {
+        Err(ArpError::InvalidAddr)
+    } else {
+        Ok((addr << 1) | u8::from(get_udid))
+    }
+}
+
+/// Addressing capability of an ARP device, as reported in its UDID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressType {
+    /// The device has a fixed address, which must be assigned back to it.
+    Fixed,
+    /// The device stores its assigned address persistently.
+    DynamicPersistent,
+    /// The device loses its assigned address on power loss.
+    DynamicVolatile,
+    /// The device uses a random number in its UDID.
+    Random,
+}
+
+/// SMBus 128-bit Unique Device Identifier.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Udid(pub [u8; 16]);
+
+impl Udid {
+    fn u16_at(&self, idx: usize) -> u16 {
+        u16::from_be_bytes([self.0[idx], self.0[idx + 1]])
+    }
+
+    /// Addressing capability of the device.
+    pub fn address_type(&self) -> AddressType {
+        match self.0[0] >> 6 {
+            0b00 => AddressType::Fixed,
+            0b01 => AddressType::DynamicPersistent,
+            0b10 => AddressType::DynamicVolatile,
+            _ => AddressType::Random,
+        }
+    }
+
+    /// Whether the device supports PEC.
+    pub fn pec_supported(&self) -> bool {
+        self.0[0] & 0x01 != 0
+    }
+
+    /// UDID version.
+    pub fn version(&self) -> u8 {
+        (self.0[1] >> 3) & 0x07
+    }
+
+    /// Silicon revision.
+    pub fn silicon_revision(&self) -> u8 {
+        self.0[1] & 0x07
+    }
+
+    /// Vendor ID, as assigned by the PCI SIG.
+    pub fn vendor_id(&self) -> u16 {
+        self.u16_at(2)
+    }
+
+    /// Device ID, as assigned by the vendor.
+    pub fn device_id(&self) -> u16 {
+        self.u16_at(4)
+    }
+
+    /// Interface flags, describing the SMBus version and protocol layers supported.
+    pub fn interface(&self) -> u16 {
+        self.u16_at(6)
+    }
+
+    /// Subsystem vendor ID.
+    pub fn subsystem_vendor_id(&self) -> u16 {
+        self.u16_at(8)
+    }
+
+    /// Subsystem device ID.
+    pub fn subsystem_device_id(&self) -> u16 {
+        self.u16_at(10)
+    }
+
+    /// Vendor-specific ID, unique per device of the same vendor and device ID.
+    pub fn vendor_specific_id(&self) -> u32 {
+        u32::from_be_bytes([self.0[12], self.0[13], self.0[14], self.0[15]])
+    }
+}
+
+/// Device as reported by the Get UDID command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UdidResponse {
+    /// Unique Device Identifier of the device.
+    pub udid: Udid,
+    /// Current 7-bit address of the device, if it has a valid one.
+    pub addr: Option<u8>,
+}
+
+/// ARP-capable device which was assigned an address during enumeration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ArpDevice {
+    /// Unique Device Identifier of the device.
+    pub udid: Udid,
+    /// 7-bit address assigned to the device.
+    pub addr: u8,
+}
+
+/// Configuration of ARP enumeration.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ArpConfig {
+    /// Addresses which may be assigned to devices. Addresses are assigned in ascending order,
+    /// unless a device already holds a valid address which is still in the pool.
+    ///
+    /// Reserved addresses are never assigned, even if present in the pool.
+    pub pool: AddressSet,
+}
+
+impl ArpConfig {
+    /// Remove addresses of devices with fixed addresses (which do not take part in ARP) from the
+    /// pool, so they are never assigned to another device. Reserved addresses are removed as well.
+    pub fn exclude(mut self, fixed: &[u8]) -> Self {
+        for &addr in fixed {
+            self.pool.remove(addr);
+        }
+        self.pool = self.target_pool();
+        self
+    }
+
+    // Pool restricted to target addresses, so reserved addresses are never assigned
+    fn target_pool(&self) -> AddressSet {
+        let mut pool = AddressSet::new();
+        for addr in self.pool.iter().filter(|&addr| is_target_addr(addr)) {
+            pool.insert(addr);
+        }
+        pool
+    }
+}
+
+impl Default for ArpConfig {
+    /// Pool of all target addresses, excluding [`SMBUS_RESERVED_ADDRS`].
+    fn default() -> Self {
+        let mut pool = AddressSet::new();
+        for addr in target_addrs() {
+            pool.insert(addr);
+        }
+
+        Self { pool }.exclude(&SMBUS_RESERVED_ADDRS)
+    }
+}
+
+// Parse a Get UDID response, verifying its byte count and PEC
+fn parse_udid<E>(cmd: u8, buf: &[u8; 19]) -> Result<UdidResponse, ArpError<E>> {
+    let pec = Pec::new()
+        .update(&[
+            DEVICE_DEFAULT_ADDR << 1,
+            cmd,
+            (DEVICE_DEFAULT_ADDR << 1) | 1,
+        ])
+        .update(&buf[..18])
+        .value();
+    if pec != buf[18] {
+        return Err(ArpError::Pec);
+    }
+    if buf[0] != UDID_BLOCK_LEN {
+        return Err(ArpError::ByteCount(buf[0]));
+    }
+
+    let mut udid = [0; 16];
+    udid.copy_from_slice(&buf[1..17]);
+    let addr = match buf[17] {
+        NO_ADDR => None,
+        addr => Some(addr >> 1),
+    };
+
+    Ok(UdidResponse {
+        udid: Udid(udid),
+        addr,
+    })
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        "doc = \"Blocking ARP controller.\"",
+        idents(embedded_hal_async(sync = "embedded_hal"))
+    ),
+    async(feature = "async", self = "asynch", "doc = \"Async ARP controller.\"")
+)]
+pub mod asynch {
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{
+        ASSIGN_ADDRESS, AddressType, ArpConfig, ArpDevice, ArpError, DEVICE_DEFAULT_ADDR, GET_UDID,
+        PREPARE_TO_ARP, RESET_DEVICE, UDID_BLOCK_LEN, Udid, UdidResponse, directed_cmd, parse_udid,
+        res_map,
+    };
+    use crate::pec::Pec;
+    use crate::reserved::is_target_addr;
+
+    /// SMBus ARP controller.
+    pub struct ArpController<I2C: I2c> {
+        i2c: I2C,
+    }
+
+    impl<I2C: I2c> ArpController<I2C> {
+        /// Create a new instance of an ARP controller.
+        pub fn new(i2c: I2C) -> Self {
+            Self { i2c }
+        }
+
+        /// Destroy this controller instance and return the underlying I2C bus instance.
+        pub fn destroy(self) -> I2C {
+            self.i2c
+        }
+
+        // Send a command with PEC to the device default address
+        async fn send_byte(&mut self, cmd: u8) -> Result<(), ArpError<I2C::Error>> {
+            let pec = Pec::new().update(&[DEVICE_DEFAULT_ADDR << 1, cmd]).value();
+            res_map(self.i2c.write(DEVICE_DEFAULT_ADDR, &[cmd, pec]).await)
+        }
+
+        // Read a UDID block with the given command, returning `None` if no device responded
+        async fn read_udid(
+            &mut self,
+            cmd: u8,
+        ) -> Result<Option<UdidResponse>, ArpError<I2C::Error>> {
+            let mut buf = [0; 19];
+            let res = self
+                .i2c
+                .write_read(DEVICE_DEFAULT_ADDR, &[cmd], &mut buf)
+                .await;
+            match res_map(res) {
+                Ok(()) => parse_udid(cmd, &buf).map(Some),
+                Err(ArpError::NoAck) => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+
+        /// Issue a Prepare to ARP command, which clears the Address Resolved flag of every
+        /// ARP-capable device so they take part in the next enumeration.
+        ///
+        /// # Errors
+        ///
+        /// If no ARP-capable device is present, [`ArpError::NoAck`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn prepare_to_arp(&mut self) -> Result<(), ArpError<I2C::Error>> {
+            self.send_byte(PREPARE_TO_ARP).await
+        }
+
+        /// Issue a general Reset Device command, which clears the Address Resolved flag of every
+        /// ARP-capable device and makes devices with dynamic volatile addresses forget theirs.
+        ///
+        /// # Errors
+        ///
+        /// If no ARP-capable device is present, [`ArpError::NoAck`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn reset_device(&mut self) -> Result<(), ArpError<I2C::Error>> {
+            self.send_byte(RESET_DEVICE).await
+        }
+
+        /// Issue a directed Reset Device command to the device at the given 7-bit address.
+        ///
+        /// # Errors
+        ///
+        /// If `addr` is reserved, not a valid 7-bit address, or below 0x10 (which would encode a
+        /// general ARP command), [`ArpError::InvalidAddr`] will be returned and nothing is sent on
+        /// the bus.
+        ///
+        /// If the device does not acknowledge the command, [`ArpError::NoAck`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn reset_device_directed(
+            &mut self,
+            addr: u8,
+        ) -> Result<(), ArpError<I2C::Error>> {
+            self.send_byte(directed_cmd(addr, false)?).await
+        }
+
+        /// Issue a general Get UDID command. If several devices have not been resolved yet, the
+        /// one with the lowest UDID wins arbitration and is returned.
+        ///
+        /// Returns `None` if no unresolved device responded.
+        ///
+        /// # Errors
+        ///
+        /// If the response fails PEC or byte count validation, [`ArpError::Pec`] or
+        /// [`ArpError::ByteCount`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn get_udid(&mut self) -> Result<Option<UdidResponse>, ArpError<I2C::Error>> {
+            self.read_udid(GET_UDID).await
+        }
+
+        /// Issue a directed Get UDID command to the device at the given 7-bit address.
+        ///
+        /// Returns `None` if the device did not respond.
+        ///
+        /// # Errors
+        ///
+        /// If `addr` is reserved, not a valid 7-bit address, or below 0x10 (which would encode a
+        /// general ARP command), [`ArpError::InvalidAddr`] will be returned and nothing is sent on
+        /// the bus.
+        ///
+        /// Otherwise, same as [`Self::get_udid`].
+        pub async fn get_udid_directed(
+            &mut self,
+            addr: u8,
+        ) -> Result<Option<UdidResponse>, ArpError<I2C::Error>> {
+            self.read_udid(directed_cmd(addr, true)?).await
+        }
+
+        /// Issue an Assign Address command, assigning the given 7-bit address to the device with
+        /// the given UDID.
+        ///
+        /// # Errors
+        ///
+        /// If `addr` is reserved or not a valid 7-bit address (see
+        /// [`is_target_addr`]), [`ArpError::InvalidAddr`] will be
+        /// returned and nothing is sent on the bus.
+        ///
+        /// If no device acknowledges the command, [`ArpError::NoAck`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn assign_address(
+            &mut self,
+            udid: &Udid,
+            addr: u8,
+        ) -> Result<(), ArpError<I2C::Error>> {
+            if !is_target_addr(addr) {
+                return Err(ArpError::InvalidAddr);
+            }
+
+            let mut buf = [0; 20];
+            buf[0] = ASSIGN_ADDRESS;
+            buf[1] = UDID_BLOCK_LEN;
+            buf[2..18].copy_from_slice(&udid.0);
+            buf[18] = addr << 1;
+            buf[19] = Pec::new()
+                .update(&[DEVICE_DEFAULT_ADDR << 1])
+                .update(&buf[..19])
+                .value();
+            res_map(self.i2c.write(DEVICE_DEFAULT_ADDR, &buf).await)
+        }
+
+        /// Enumerate all ARP-capable devices and assign each an address from the pool in `config`,
+        /// storing them in `devices`.
+        ///
+        /// Devices with a fixed address are assigned their own address. Other devices keep their
+        /// current address if it is still available in the pool, or are assigned the lowest
+        /// available address otherwise.
+        ///
+        /// Returns the number of devices enumerated.
+        ///
+        /// # Errors
+        ///
+        /// If the pool runs out of addresses, [`ArpError::PoolExhausted`] will be returned.
+        ///
+        /// If more devices respond than fit in `devices`, [`ArpError::TooManyDevices`] will be
+        /// returned.
+        ///
+        /// In either case, devices enumerated so far remain stored in `devices`.
+        ///
+        /// If any ARP transaction fails, its error will be returned.
+        pub async fn enumerate(
+            &mut self,
+            config: &ArpConfig,
+            devices: &mut [ArpDevice],
+        ) -> Result<usize, ArpError<I2C::Error>> {
+            let mut pool = config.target_pool();
+            let mut count = 0;
+
+            match self.prepare_to_arp().await {
+                Ok(()) => {}
+                // No ARP-capable devices on the bus
+                Err(ArpError::NoAck) => return Ok(0),
+                Err(e) => return Err(e),
+            }
+
+            while let Some(response) = self.get_udid().await? {
+                let addr = match (response.udid.address_type(), response.addr) {
+                    (AddressType::Fixed, Some(addr)) => addr,
+                    (_, Some(addr)) if pool.contains(addr) => addr,
+                    _ => pool.iter().next().ok_or(ArpError::PoolExhausted)?,
+                };
+
+                let device = devices.get_mut(count).ok_or(ArpError::TooManyDevices)?;
+                self.assign_address(&response.udid, addr).await?;
+                pool.remove(addr);
+                *device = ArpDevice {
+                    udid: response.udid,
+                    addr,
+                };
+                count += 1;
+            }
+
+            Ok(count)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Get UDID response for a dynamic volatile, PEC-capable device at 0x20
+    fn udid_response() -> [u8; 19] {
+        let mut buf = [0; 19];
+        buf[0] = UDID_BLOCK_LEN;
+        buf[1] = 0x81;
+        buf[2..17].copy_from_slice(&[
+            0x08, 0x10, 0xDE, 0x12, 0x34, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03,
+            0x04,
+        ]);
+        buf[17] = (0x20 << 1) | 1;
+        buf[18] = Pec::new()
+            .update(&[
+                DEVICE_DEFAULT_ADDR << 1,
+                GET_UDID,
+                (DEVICE_DEFAULT_ADDR << 1) | 1,
+            ])
+            .update(&buf[..18])
+            .value();
+        buf
+    }
+
+    #[test]
+    fn parse_udid_valid() {
+        let response = parse_udid::<()>(GET_UDID, &udid_response()).unwrap();
+        assert_eq!(response.addr, Some(0x20));
+        assert_eq!(response.udid.address_type(), AddressType::DynamicVolatile);
+        assert!(response.udid.pec_supported());
+        assert_eq!(response.udid.vendor_id(), 0x10DE);
+        assert_eq!(response.udid.device_id(), 0x1234);
+        assert_eq!(response.udid.vendor_specific_id(), 0x0102_0304);
+    }
+
+    #[test]
+    fn parse_udid_no_addr() {
+        let mut buf = udid_response();
+        buf[17] = NO_ADDR;
+        buf[18] = Pec::new()
+            .update(&[
+                DEVICE_DEFAULT_ADDR << 1,
+                GET_UDID,
+                (DEVICE_DEFAULT_ADDR << 1) | 1,
+            ])
+            .update(&buf[..18])
+            .value();
+        assert_eq!(parse_udid::<()>(GET_UDID, &buf).unwrap().addr, None);
+    }
+
+    #[test]
+    fn parse_udid_rejects_bad_pec() {
+        let mut buf = udid_response();
+        buf[18] ^= 0x01;
+        assert!(matches!(
+            parse_udid::<()>(GET_UDID, &buf),
+            Err(ArpError::Pec)
+        ));
+
+        // The PEC also covers the command, so a response to another command must not validate
+        assert!(matches!(
+            parse_udid::<()>(directed_cmd::<()>(0x20, true).unwrap(), &udid_response()),
+            Err(ArpError::Pec)
+        ));
+    }
+
+    #[test]
+    fn parse_udid_rejects_bad_byte_count() {
+        let mut buf = udid_response();
+        buf[0] = 16;
+        buf[18] = Pec::new()
+            .update(&[
+                DEVICE_DEFAULT_ADDR << 1,
+                GET_UDID,
+                (DEVICE_DEFAULT_ADDR << 1) | 1,
+            ])
+            .update(&buf[..18])
+            .value();
+        assert!(matches!(
+            parse_udid::<()>(GET_UDID, &buf),
+            Err(ArpError::ByteCount(16))
+        ));
+    }
+
+    #[test]
+    fn directed_cmd_rejects_general_and_reserved() {
+        for addr in [0x00, 0x01, 0x07, 0x08, 0x0F, 0x78, 0x7F, 0x80] {
+            assert!(matches!(
+                directed_cmd::<()>(addr, false),
+                Err(ArpError::InvalidAddr)
+            ));
+        }
+        assert_eq!(directed_cmd::<()>(0x10, false).unwrap(), 0x20);
+        assert_eq!(directed_cmd::<()>(0x77, true).unwrap(), 0xEF);
+    }
+
+    #[test]
+    fn pool_excludes_reserved() {
+        let config = ArpConfig::default();
+        assert!(config.pool.iter().all(is_target_addr));
+        for addr in SMBUS_RESERVED_ADDRS {
+            assert!(!config.pool.contains(addr));
+        }
+
+        let mut pool = AddressSet::new();
+        for addr in [0x00, 0x07, 0x30, 0x50, 0x78] {
+            pool.insert(addr);
+        }
+        let config = ArpConfig { pool }.exclude(&[0x50]);
+        assert_eq!(config.pool.len(), 1);
+        assert!(config.pool.contains(0x30));
+    }
+}
@@ -0,0 +1,164 @@
+//! Reading the I2C Device ID of a target via the reserved Device ID address.
+//!
+//! The Device ID is read by writing the target address to the reserved address `1111 100`, then
+//! reading three bytes from the same reserved address after a repeated START.
+
+use embedded_hal::i2c;
+
+use crate::ReservedAddress;
+
+// Reserved Device ID address (`1111 100x`)
+const DEVICE_ID_ADDR: u8 = ReservedAddress::DeviceId.addr();
+
+// Manufacturer names indexed by manufacturer ID, as assigned in the I2C specification
+const MANUFACTURERS: [&str; 14] = [
+    "NXP Semiconductors",
+    "NXP Semiconductors (reserved)",
+    "NXP Semiconductors (reserved)",
+    "NXP Semiconductors (reserved)",
+    "Ramtron International",
+    "Analog Devices",
+    "STMicroelectronics",
+    "ON Semiconductor",
+    "Sprintek Corporation",
+    "ESPROS Photonics AG",
+    "Fujitsu Semiconductor",
+    "Flir",
+    "O2Micro",
+    "Atmel",
+];
+
+/// Device ID read error.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DeviceIdError<E> {
+    /// No device on the bus acknowledged the Device ID address, so none implement it.
+    NoAckDeviceId,
+    /// The target address was not acknowledged, so no device at that address implements Device ID.
+    NoAckTarget,
+    /// The Device ID read was not acknowledged, but the HAL could not tell whether the Device ID
+    /// address or the target address was refused.
+    NoAck,
+    /// The provided address is not a valid 7-bit address.
+    InvalidAddr,
+    /// Other I2C error was encountered.
+    I2C(E),
+}
+
+/// Decoded I2C Device ID.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DeviceId {
+    /// 12-bit manufacturer ID.
+    pub manufacturer: u16,
+    /// 9-bit part identification, assigned by the manufacturer.
+    pub part: u16,
+    /// 3-bit die revision.
+    pub revision: u8,
+}
+
+impl DeviceId {
+    /// Decode a Device ID from the three bytes read from a target.
+    pub fn from_bytes(bytes: [u8; 3]) -> Self {
+        let raw = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        Self {
+            manufacturer: (raw >> 12) as u16,
+            part: ((raw >> 3) & 0x1FF) as u16,
+            revision: (raw & 0x7) as u8,
+        }
+    }
+
+    /// Name of the manufacturer, if it is one of the known assigned manufacturer IDs.
+    pub fn manufacturer_name(&self) -> Option<&'static str> {
+        MANUFACTURERS.get(usize::from(self.manufacturer)).copied()
+    }
+}
+
+fn res_map<E: i2c::Error>(res: Result<(), E>) -> Result<(), DeviceIdError<E>> {
+    res.map_err(|e| match e.kind() {
+        i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address) => {
+            DeviceIdError::NoAckDeviceId
+        }
+        i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Data) => DeviceIdError::NoAckTarget,
+        i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Unknown) => DeviceIdError::NoAck,
+        _ => DeviceIdError::I2C(e),
+    })
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch")
+)]
+mod asynch {
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{DEVICE_ID_ADDR, DeviceId, DeviceIdError, res_map};
+    use crate::asynch::GeneralCall;
+
+    impl<I2C: I2c, D> GeneralCall<I2C, D> {
+        /// Read the Device ID of the target at the given 7-bit address.
+        ///
+        /// # Errors
+        ///
+        /// If `addr` is not a valid 7-bit address, [`DeviceIdError::InvalidAddr`] will be returned
+        /// and nothing is sent on the bus.
+        ///
+        /// If no device implements Device ID, [`DeviceIdError::NoAckDeviceId`] will be returned.
+        ///
+        /// If the target does not exist or does not implement Device ID,
+        /// [`DeviceIdError::NoAckTarget`] will be returned.
+        ///
+        /// If the HAL cannot tell which of the two was refused, [`DeviceIdError::NoAck`] will be
+        /// returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn read_device_id(
+            &mut self,
+            addr: u8,
+        ) -> Result<DeviceId, DeviceIdError<I2C::Error>> {
+            if addr > 0x7F {
+                return Err(DeviceIdError::InvalidAddr);
+            }
+
+            let mut bytes = [0; 3];
+            let res = self
+                .i2c
+                .write_read(DEVICE_ID_ADDR, &[addr << 1], &mut bytes)
+                .await;
+            res_map(res)?;
+            Ok(DeviceId::from_bytes(bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceId;
+
+    #[test]
+    fn from_bytes_splits_fields() {
+        // Manufacturer 0x005, part 0x1A5, revision 3
+        let id = DeviceId::from_bytes([0x00, 0x5D, 0x2B]);
+        assert_eq!(
+            id,
+            DeviceId {
+                manufacturer: 0x005,
+                part: 0x1A5,
+                revision: 3,
+            }
+        );
+        assert_eq!(id.manufacturer_name(), Some("Analog Devices"));
+    }
+
+    #[test]
+    fn from_bytes_all_ones() {
+        let id = DeviceId::from_bytes([0xFF; 3]);
+        assert_eq!(id.manufacturer, 0xFFF);
+        assert_eq!(id.part, 0x1FF);
+        assert_eq!(id.revision, 7);
+        assert_eq!(id.manufacturer_name(), None);
+    }
+}
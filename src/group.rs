@@ -0,0 +1,364 @@
+//! PMBus Group Command Protocol.
+//!
+//! A group command sends one command to each of several devices in a single transaction, separated
+//! by repeated STARTs and terminated by a single STOP. Every device waits for the STOP before
+//! executing its command, so all of them act simultaneously.
+//!
+//! Since [`I2c::transaction`](embedded_hal::i2c::I2c::transaction) only addresses a single device,
+//! group commands are issued over the [`MultiAddrTransaction`] trait, which HALs capable of
+//! repeated STARTs to different addresses can implement. [`Sequential`] provides a fallback for
+//! any other bus.
+
+pub use blocking::{MultiAddrTransaction, Sequential};
+
+/// Group command error.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GroupError<E> {
+    /// A device did not acknowledge its address, command or data. Whether the remaining devices
+    /// received their commands depends on the HAL.
+    NoAck,
+    /// The address of a device is not a valid 7-bit address.
+    InvalidAddr,
+    /// Other I2C error was encountered.
+    I2C(E),
+}
+
+/// Command sent to a single device as part of a group command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GroupMessage<'a> {
+    /// 7-bit address of the device.
+    pub addr: u8,
+    /// PMBus command code.
+    pub cmd: u8,
+    /// Data bytes following the command code.
+    pub data: &'a [u8],
+}
+
+/// Builder of a group command addressing up to `N` devices.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GroupCommand<'a, const N: usize> {
+    msgs: [GroupMessage<'a>; N],
+    len: usize,
+    pec: bool,
+}
+
+impl<'a, const N: usize> GroupCommand<'a, N> {
+    /// Create a new, empty group command without PEC.
+    pub const fn new() -> Self {
+        Self {
+            msgs: [GroupMessage {
+                addr: 0,
+                cmd: 0,
+                data: &[],
+            }; N],
+            len: 0,
+            pec: false,
+        }
+    }
+
+    /// Enable or disable Packet Error Checking. When enabled, each device's command is followed by
+    /// a PEC byte computed over that device's address, command and data.
+    pub fn set_pec(&mut self, enable: bool) -> &mut Self {
+        self.pec = enable;
+        self
+    }
+
+    /// Whether Packet Error Checking is enabled.
+    pub fn pec(&self) -> bool {
+        self.pec
+    }
+
+    /// Add a command for the device at the given 7-bit address. Devices receive their commands in
+    /// the order they were added.
+    ///
+    /// Returns `false` if the group is already full.
+    pub fn push(&mut self, addr: u8, cmd: u8, data: &'a [u8]) -> bool {
+        match self.msgs.get_mut(self.len) {
+            Some(msg) => {
+                *msg = GroupMessage { addr, cmd, data };
+                self.len += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Commands added to the group so far.
+    pub fn messages(&self) -> &[GroupMessage<'a>] {
+        &self.msgs[..self.len]
+    }
+
+    /// Remove all commands from the group.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for GroupCommand<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        "doc = \"Blocking multi-address transactions and group commands.\"",
+        idents(embedded_hal_async(sync = "embedded_hal"))
+    ),
+    async(
+        feature = "async",
+        self = "asynch",
+        "doc = \"Async multi-address transactions and group commands.\""
+    )
+)]
+pub mod asynch {
+    use core::slice;
+
+    use embedded_hal::i2c::{self, ErrorType, Operation};
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{GroupCommand, GroupError};
+    use crate::pec::Pec;
+
+    /// Extension trait for buses able to address several devices in a single transaction.
+    #[allow(async_fn_in_trait)]
+    pub trait MultiAddrTransaction: ErrorType {
+        /// Execute each segment's operations on its 7-bit address, with a repeated START between
+        /// segments and a single STOP after the last one.
+        ///
+        /// Within a segment, operations follow the same contract as
+        /// [`I2c::transaction`].
+        async fn multi_addr_transaction(
+            &mut self,
+            segments: &mut [(u8, &mut [Operation<'_>])],
+        ) -> Result<(), Self::Error>;
+
+        /// Issue a group command, so that every device in the group executes its command at the
+        /// same time.
+        ///
+        /// An empty group does nothing.
+        ///
+        /// # Errors
+        ///
+        /// If the address of any device is not a valid 7-bit address, [`GroupError::InvalidAddr`]
+        /// will be returned and nothing is sent on the bus.
+        ///
+        /// If any device does not acknowledge, [`GroupError::NoAck`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        async fn group_command<const N: usize>(
+            &mut self,
+            group: &GroupCommand<'_, N>,
+        ) -> Result<(), GroupError<Self::Error>> {
+            let msgs = group.messages();
+            if msgs.is_empty() {
+                return Ok(());
+            }
+
+            let mut pecs = [0; N];
+            for (pec, msg) in pecs.iter_mut().zip(msgs) {
+                if msg.addr > 0x7F {
+                    return Err(GroupError::InvalidAddr);
+                }
+                *pec = Pec::new()
+                    .update(&[msg.addr << 1, msg.cmd])
+                    .update(msg.data)
+                    .value();
+            }
+
+            // Command, data and PEC writes are merged into a single write per device. Empty data
+            // is left out, since many HALs reject zero-length writes
+            let mut lens = [0; N];
+            let mut ops: [[Operation<'_>; 3]; N] = core::array::from_fn(|i| {
+                let mut ops = [const { Operation::Write(&[]) }; 3];
+                if let Some(msg) = msgs.get(i) {
+                    let bufs = [
+                        Some(slice::from_ref(&msg.cmd)),
+                        (!msg.data.is_empty()).then_some(msg.data),
+                        group.pec().then_some(slice::from_ref(&pecs[i])),
+                    ];
+                    for (op, buf) in ops.iter_mut().zip(bufs.into_iter().flatten()) {
+                        *op = Operation::Write(buf);
+                        lens[i] += 1;
+                    }
+                }
+                ops
+            });
+            let mut heads = msgs.iter().map(|msg| msg.addr).zip(lens);
+            let mut segments = ops.each_mut().map(|ops| {
+                let (addr, len) = heads.next().unwrap_or_default();
+                (addr, &mut ops[..len])
+            });
+
+            self.multi_addr_transaction(&mut segments[..msgs.len()])
+                .await
+                .map_err(|e| match i2c::Error::kind(&e) {
+                    i2c::ErrorKind::NoAcknowledge(_) => GroupError::NoAck,
+                    _ => GroupError::I2C(e),
+                })
+        }
+    }
+
+    /// Fallback [`MultiAddrTransaction`] for buses unable to address several devices in a single
+    /// transaction, which performs each segment as its own transaction instead.
+    ///
+    /// Each segment is terminated by a STOP, so devices execute group commands as soon as they
+    /// receive them rather than simultaneously, and another master may take the bus between two
+    /// segments. Only use this when devices need not act at the same instant.
+    pub struct Sequential<I2C>(pub I2C);
+
+    impl<I2C: ErrorType> ErrorType for Sequential<I2C> {
+        type Error = I2C::Error;
+    }
+
+    impl<I2C: I2c> MultiAddrTransaction for Sequential<I2C> {
+        async fn multi_addr_transaction(
+            &mut self,
+            segments: &mut [(u8, &mut [Operation<'_>])],
+        ) -> Result<(), Self::Error> {
+            for (addr, ops) in segments.iter_mut() {
+                self.0.transaction(*addr, ops).await?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c::{ErrorKind, ErrorType, NoAcknowledgeSource, Operation};
+
+    use super::*;
+    use crate::pec::Pec;
+
+    const MAX_SEGMENTS: usize = 4;
+    const MAX_BYTES: usize = 8;
+
+    // Records the bytes written to each segment, flattened, and the number of operations
+    #[derive(Default)]
+    struct Recorder {
+        calls: usize,
+        segments: usize,
+        addrs: [u8; MAX_SEGMENTS],
+        ops: [usize; MAX_SEGMENTS],
+        bytes: [[u8; MAX_BYTES]; MAX_SEGMENTS],
+        lens: [usize; MAX_SEGMENTS],
+        fail: Option<ErrorKind>,
+    }
+
+    impl Recorder {
+        fn segment(&self, i: usize) -> (u8, usize, &[u8]) {
+            (self.addrs[i], self.ops[i], &self.bytes[i][..self.lens[i]])
+        }
+    }
+
+    impl ErrorType for Recorder {
+        type Error = ErrorKind;
+    }
+
+    impl MultiAddrTransaction for Recorder {
+        fn multi_addr_transaction(
+            &mut self,
+            segments: &mut [(u8, &mut [Operation<'_>])],
+        ) -> Result<(), ErrorKind> {
+            self.calls += 1;
+            self.segments = segments.len();
+            for (i, (addr, ops)) in segments.iter().enumerate() {
+                self.addrs[i] = *addr;
+                self.ops[i] = ops.len();
+                for op in ops.iter() {
+                    let Operation::Write(buf) = op else {
+                        panic!("group commands only write");
+                    };
+                    assert!(!buf.is_empty(), "zero-length write");
+                    self.bytes[i][self.lens[i]..][..buf.len()].copy_from_slice(buf);
+                    self.lens[i] += buf.len();
+                }
+            }
+            self.fail.map_or(Ok(()), Err)
+        }
+    }
+
+    fn pec(addr: u8, bytes: &[u8]) -> u8 {
+        Pec::new().update(&[addr << 1]).update(bytes).value()
+    }
+
+    #[test]
+    fn without_pec() {
+        let mut group = GroupCommand::<4>::new();
+        group.push(0x40, 0x01, &[0x80]);
+        group.push(0x41, 0x03, &[]);
+        group.push(0x42, 0x21, &[0x34, 0x12]);
+
+        let mut bus = Recorder::default();
+        bus.group_command(&group).unwrap();
+        assert_eq!(bus.calls, 1);
+        assert_eq!(bus.segments, 3);
+        assert_eq!(bus.segment(0), (0x40, 2, &[0x01, 0x80][..]));
+        assert_eq!(bus.segment(1), (0x41, 1, &[0x03][..]));
+        assert_eq!(bus.segment(2), (0x42, 2, &[0x21, 0x34, 0x12][..]));
+    }
+
+    #[test]
+    fn with_pec() {
+        let mut group = GroupCommand::<2>::new();
+        group.set_pec(true);
+        group.push(0x40, 0x01, &[0x80]);
+        group.push(0x41, 0x03, &[]);
+
+        let mut bus = Recorder::default();
+        bus.group_command(&group).unwrap();
+        assert_eq!(bus.segments, 2);
+        assert_eq!(
+            bus.segment(0),
+            (0x40, 3, &[0x01, 0x80, pec(0x40, &[0x01, 0x80])][..])
+        );
+        assert_eq!(bus.segment(1), (0x41, 2, &[0x03, pec(0x41, &[0x03])][..]));
+    }
+
+    #[test]
+    fn empty_group_does_nothing() {
+        let mut bus = Recorder::default();
+        bus.group_command(&GroupCommand::<2>::new()).unwrap();
+        assert_eq!(bus.calls, 0);
+    }
+
+    #[test]
+    fn invalid_addr_sends_nothing() {
+        let mut group = GroupCommand::<2>::new();
+        group.push(0x40, 0x01, &[0x80]);
+        group.push(0x80, 0x01, &[0x80]);
+
+        let mut bus = Recorder::default();
+        assert!(matches!(
+            bus.group_command(&group),
+            Err(GroupError::InvalidAddr)
+        ));
+        assert_eq!(bus.calls, 0);
+    }
+
+    #[test]
+    fn maps_nack() {
+        let mut group = GroupCommand::<1>::new();
+        group.push(0x40, 0x01, &[]);
+
+        let mut bus = Recorder {
+            fail: Some(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)),
+            ..Default::default()
+        };
+        assert!(matches!(bus.group_command(&group), Err(GroupError::NoAck)));
+
+        let mut bus = Recorder {
+            fail: Some(ErrorKind::Bus),
+            ..Default::default()
+        };
+        assert!(matches!(
+            bus.group_command(&group),
+            Err(GroupError::I2C(ErrorKind::Bus))
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+//! SMBus Host Notify protocol.
+//!
+//! A device which needs the host's attention can become a master and write its own address
+//! followed by a 16-bit status word to the SMBus Host address.
+
+/// SMBus Host address.
+pub const HOST_ADDR: u8 = 0x08;
+
+/// SMBus Host Notify message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HostNotify {
+    /// 7-bit address of the notifying device.
+    pub device_addr: u8,
+    /// Device-specific status word.
+    pub status: u16,
+}
+
+impl HostNotify {
+    /// Encode this message into the bytes written to the host address.
+    ///
+    /// Returns `None` if the device address is not a valid 7-bit address.
+    pub fn encode(&self) -> Option<[u8; 3]> {
+        if self.device_addr > 0x7F {
+            return None;
+        }
+
+        let [low, high] = self.status.to_le_bytes();
+        Some([self.device_addr << 1, low, high])
+    }
+
+    /// Decode a message from the bytes received at the host address.
+    ///
+    /// Returns `None` if the bytes are not a well-formed Host Notify message.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        match *bytes {
+            [addr, low, high] if addr & 1 == 0 => Some(Self {
+                device_addr: addr >> 1,
+                status: u16::from_le_bytes([low, high]),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch")
+)]
+mod asynch {
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{HOST_ADDR, HostNotify};
+    use crate::Error;
+    use crate::asynch::GeneralCall;
+
+    impl<I2C: I2c, D> GeneralCall<I2C, D> {
+        /// Send a Host Notify message to the SMBus Host, on behalf of a target-capable device
+        /// acting as a master.
+        ///
+        /// # Errors
+        ///
+        /// If the device address is not a valid 7-bit address, [`Error::InvalidAddr`] will be
+        /// returned and nothing is sent on the bus.
+        ///
+        /// If the host does not acknowledge the message or any other I2C error occurs, the
+        /// underlying error will be returned, since this is not a general call.
+        pub async fn host_notify(&mut self, notify: &HostNotify) -> Result<(), Error<I2C::Error>> {
+            let bytes = notify.encode().ok_or(Error::InvalidAddr)?;
+            self.i2c.write(HOST_ADDR, &bytes).await.map_err(Error::I2C)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HostNotify;
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let notify = HostNotify {
+            device_addr: 0x42,
+            status: 0x1234,
+        };
+        let bytes = notify.encode().unwrap();
+        assert_eq!(bytes, [0x84, 0x34, 0x12]);
+        assert_eq!(HostNotify::decode(&bytes), Some(notify));
+    }
+
+    #[test]
+    fn encode_rejects_invalid_addr() {
+        let notify = HostNotify {
+            device_addr: 0x80,
+            status: 0,
+        };
+        assert_eq!(notify.encode(), None);
+    }
+
+    #[test]
+    fn decode_rejects_malformed() {
+        // Read bit set in the address byte
+        assert_eq!(HostNotify::decode(&[0x85, 0x34, 0x12]), None);
+        // Wrong lengths
+        assert_eq!(HostNotify::decode(&[0x84, 0x34]), None);
+        assert_eq!(HostNotify::decode(&[0x84, 0x34, 0x12, 0x00]), None);
+    }
+}
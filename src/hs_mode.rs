@@ -0,0 +1,106 @@
+//! Hs-mode master code transmission.
+//!
+//! Before switching to Hs-mode, a master transmits its master code (`0000 1xxx`) at Fast-mode
+//! speed. Master codes are used for arbitration between Hs-mode masters and no device is allowed
+//! to acknowledge them.
+
+use crate::ReservedAddress;
+
+/// Hs-mode master code, identifying one of up to eight Hs-mode masters on a bus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MasterCode(u8);
+
+impl MasterCode {
+    /// Create a master code from its 3-bit value (0-7). Returns `None` if out of range.
+    pub const fn new(code: u8) -> Option<Self> {
+        if code <= 7 { Some(Self(code)) } else { None }
+    }
+
+    /// The 3-bit value of this master code.
+    pub const fn value(&self) -> u8 {
+        self.0
+    }
+
+    /// The full byte transmitted on the bus (`0000 1xxx`).
+    pub const fn byte(&self) -> u8 {
+        (ReservedAddress::HsMasterCode.addr() << 1) | self.0
+    }
+}
+
+impl TryFrom<u8> for MasterCode {
+    type Error = u8;
+
+    fn try_from(code: u8) -> Result<Self, u8> {
+        Self::new(code).ok_or(code)
+    }
+}
+
+impl From<MasterCode> for u8 {
+    fn from(code: MasterCode) -> Self {
+        code.value()
+    }
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch")
+)]
+mod asynch {
+    use embedded_hal_async::i2c::I2c;
+
+    use super::MasterCode;
+    use crate::asynch::GeneralCall;
+    use crate::{Error, nack_res_map};
+
+    impl<I2C: I2c, D> GeneralCall<I2C, D> {
+        /// Transmit an Hs-mode master code. The bus must currently be running at Fast-mode speed
+        /// or slower; switching the HAL to Hs-mode afterwards is up to the caller.
+        ///
+        /// The master code is expected to be NACKed, so a NACK is treated as success.
+        ///
+        /// # Errors
+        ///
+        /// If any I2C error other than a NACK occurs, such as losing arbitration to another Hs-mode
+        /// master, the underlying error will be returned.
+        pub async fn master_code(&mut self, code: MasterCode) -> Result<(), Error<I2C::Error>> {
+            // The LSB of the master code byte falls on the R/W bit
+            let addr = code.byte() >> 1;
+            let res = if code.byte() & 1 == 1 {
+                // Zero-length reads are rejected by many HALs, and the NACK ends the read before
+                // any byte is clocked in anyway
+                self.i2c.read(addr, &mut [0]).await
+            } else {
+                self.i2c.write(addr, &[]).await
+            };
+            nack_res_map(res)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MasterCode;
+
+    #[test]
+    fn new_bounds() {
+        assert_eq!(MasterCode::new(0).map(|c| c.value()), Some(0));
+        assert_eq!(MasterCode::new(7).map(|c| c.value()), Some(7));
+        assert_eq!(MasterCode::new(8), None);
+        assert_eq!(MasterCode::new(0xFF), None);
+        assert_eq!(MasterCode::try_from(8), Err(8));
+    }
+
+    #[test]
+    fn byte_is_0000_1xxx() {
+        for value in 0..=7 {
+            let code = MasterCode::new(value).unwrap();
+            assert_eq!(code.value(), value);
+            assert_eq!(code.byte(), 0b0000_1000 | value);
+            assert_eq!(u8::from(code), value);
+        }
+    }
+}
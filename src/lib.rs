@@ -1,18 +1,53 @@
 //! A platform agnostic Rust driver for making I2C general calls.
 //!
-//! Currently supports software general calls (reset and address latching).
+//! Currently supports software general calls (reset and address latching) and hardware general
+//! calls (master address announcement).
+//!
+//! The blocking driver is always available as [`GeneralCall`] (also exported from [`blocking`]).
+//! Alternatively, [`GeneralCallExt`] allows issuing general calls directly on a borrowed bus.
+//! Enabling the `async` feature additionally exposes `asynch::GeneralCall`, so both flavors can
+//! be used side by side.
 #![no_std]
 
+use core::num::NonZeroU8;
 use embedded_hal::i2c;
-#[cfg(not(feature = "async"))]
-use embedded_hal::i2c::I2c;
+
+pub use address_set::AddressSet;
+pub use blocking::{GeneralCall, GeneralCallExt};
+pub use reserved::ReservedAddress;
+
+pub mod address_set;
+pub mod alert;
+pub mod arp;
+pub mod device_id;
+pub mod group;
+pub mod host_notify;
+pub mod hs_mode;
+pub mod pec;
+pub mod policy;
+pub mod recovery;
+pub mod reserved;
+pub mod retry;
+pub mod scan;
+mod start_byte;
+pub mod target;
 #[cfg(feature = "async")]
-use embedded_hal_async::i2c::I2c as AsyncI2c;
+mod timeout;
+pub mod verify;
+pub mod zone;
 
 // General call (aka broadcast) address
-const GENERAL_CALL_ADDR: u8 = 0x00;
+const GENERAL_CALL_ADDR: u8 = ReservedAddress::GeneralCall.addr();
+
+/// Default time, in microseconds, to wait for devices to settle after a reset or address latch.
+pub const DEFAULT_SETTLE_TIME_US: u32 = 1_000;
 
-// Only two specified commands
+/// Default timeout, in microseconds, for async general calls. Matches the upper bound of the
+/// SMBus clock low timeout (25-35 ms), after which targets are required to release the bus.
+#[cfg(feature = "async")]
+pub const DEFAULT_TIMEOUT_US: u32 = 35_000;
+
+// Only two specified software commands
 enum Command {
     Reset,
     LatchAddr,
@@ -31,116 +66,564 @@ impl From<Command> for u8 {
 #[derive(Clone, Copy, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error<E> {
-    /// No device on the bus acknowledged the general call.
+    /// No device on the bus acknowledged the general call address, so none are listening to
+    /// general calls at all. This can be checked beforehand without side effects with
+    /// [`GeneralCall::probe`].
     NoAckCall,
     /// At least one device on the bus acknowledged the general call, but not the specific command.
     NoAckCmd,
+    /// The general call was not acknowledged, but the HAL could not tell whether the address or
+    /// the command was refused.
+    ///
+    /// Enabling [`GeneralCall::set_resolve_unknown_nack`] resolves this into [`Error::NoAckCall`]
+    /// or [`Error::NoAckCmd`] instead.
+    NoAck,
+    /// At least one device on the bus acknowledged the general call, but refused either the
+    /// command or one of the data bytes following it.
+    ///
+    /// This variant deliberately carries no byte position. `embedded-hal` only reports whether the
+    /// address or a data byte was refused, so whenever data follows the command, a refused command
+    /// byte and a refused Nth data byte cannot be told apart and are both reported here.
+    NoAckData,
+    /// The provided address is not a valid 7-bit address, or is reserved.
+    InvalidAddr,
+    /// The provided command is not a valid software general call command.
+    InvalidCmd,
+    /// The general call did not complete in time.
+    Timeout,
     /// Other I2C error was encountered.
     I2C(E),
 }
 
-/// I2C general call driver.
-#[maybe_async_cfg::maybe(
-    sync(
-        cfg(not(feature = "async")),
-        self = "GeneralCall",
-        idents(AsyncI2c(sync = "I2c"))
-    ),
-    async(feature = "async", keep_self)
-)]
-pub struct GeneralCall<I2C: AsyncI2c> {
-    i2c: I2C,
+// Second byte of a hardware general call: the master's 7-bit address with the LSB set
+fn hardware_call_byte<E>(master_addr: u8) -> Result<u8, Error<E>> {
+    if !reserved::is_target_addr(master_addr) {
+        Err(Error::InvalidAddr)
+    } else {
+        Ok((master_addr << 1) | 1)
+    }
+}
+
+// Second byte of a software general call: must be nonzero with the LSB clear
+fn software_call_byte<E>(cmd: NonZeroU8) -> Result<u8, Error<E>> {
+    if cmd.get() & 1 == 1 {
+        Err(Error::InvalidCmd)
+    } else {
+        Ok(cmd.get())
+    }
+}
+
+fn res_map<E: i2c::Error>(res: Result<(), E>) -> Result<(), Error<E>> {
+    res.map_err(|e| match e.kind() {
+        i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address) => Error::NoAckCall,
+        i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Data) => Error::NoAckCmd,
+        i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Unknown) => Error::NoAck,
+        _ => Error::I2C(e),
+    })
+}
+
+// Whether a probe was acknowledged, passing through any error other than a NACK
+fn ack_res_map<E: i2c::Error>(res: Result<(), E>) -> Result<bool, Error<E>> {
+    match res {
+        Ok(()) => Ok(true),
+        Err(e) if matches!(e.kind(), i2c::ErrorKind::NoAcknowledge(_)) => Ok(false),
+        Err(e) => Err(Error::I2C(e)),
+    }
+}
+
+// For reserved bytes no device may acknowledge, so a NACK is success. A misbehaving device ACKing
+// does not prevent the byte from serving its purpose either.
+fn nack_res_map<E: i2c::Error>(res: Result<(), E>) -> Result<(), Error<E>> {
+    match res {
+        Err(e) if !matches!(e.kind(), i2c::ErrorKind::NoAcknowledge(_)) => Err(Error::I2C(e)),
+        _ => Ok(()),
+    }
+}
+
+// Same as `res_map`, but a data NACK can no longer be pinned on the command byte alone
+fn payload_res_map<E: i2c::Error>(res: Result<(), E>, data: &[u8]) -> Result<(), Error<E>> {
+    match res_map(res) {
+        Err(Error::NoAckCmd) if !data.is_empty() => Err(Error::NoAckData),
+        res => res,
+    }
 }
 
 #[maybe_async_cfg::maybe(
     sync(
-        cfg(not(feature = "async")),
-        self = "GeneralCall",
-        idents(AsyncI2c(sync = "I2c"))
+        self = "blocking",
+        "doc = \"Blocking general call API over [`embedded_hal::i2c::I2c`].\"",
+        idents(embedded_hal_async(sync = "embedded_hal"))
     ),
-    async(feature = "async", keep_self)
+    async(
+        feature = "async",
+        self = "asynch",
+        "doc = \"Async general call API over [`embedded_hal_async::i2c::I2c`].\""
+    )
 )]
-impl<E: i2c::Error, I2C: AsyncI2c<Error = E>> GeneralCall<I2C> {
-    fn res_map(res: Result<(), E>) -> Result<(), Error<E>> {
-        res.map_err(|e| match e.kind() {
-            i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address) => Error::NoAckCall,
-            i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Data) => Error::NoAckCmd,
-            _ => Error::I2C(e),
-        })
+pub mod asynch {
+    use embedded_hal::i2c::{self, Operation};
+    use embedded_hal_async::delay::DelayNs;
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{
+        Command, DEFAULT_SETTLE_TIME_US, Error, GENERAL_CALL_ADDR, NonZeroU8, ack_res_map,
+        hardware_call_byte, payload_res_map, res_map, software_call_byte,
+    };
+    use crate::pec::Pec;
+
+    // Probe for a device with a zero-length write, returning whether it acknowledged
+    pub(crate) async fn probe_addr<I2C: I2c>(
+        i2c: &mut I2C,
+        addr: u8,
+    ) -> Result<bool, Error<I2C::Error>> {
+        ack_res_map(i2c.write(addr, &[]).await)
     }
 
-    /// Create a new instance of an I2C general call driver.
-    pub fn new(i2c: I2C) -> Self {
-        Self { i2c }
+    // Write a software general call command and data, followed by its PEC byte if `pec` is set
+    pub(crate) async fn write_general_call<I2C: I2c>(
+        i2c: &mut I2C,
+        pec: bool,
+        cmd: u8,
+        data: &[u8],
+    ) -> Result<(), Error<I2C::Error>> {
+        let pec_byte = [Pec::new()
+            .update(&[GENERAL_CALL_ADDR << 1, cmd])
+            .update(data)
+            .value()];
+        // Only non-empty operations are sent, as some HALs reject zero-length transfers
+        let res = match (pec, data.is_empty()) {
+            (false, true) => i2c.write(GENERAL_CALL_ADDR, &[cmd]).await,
+            (true, true) => i2c.write(GENERAL_CALL_ADDR, &[cmd, pec_byte[0]]).await,
+            (false, false) => {
+                i2c.transaction(
+                    GENERAL_CALL_ADDR,
+                    &mut [Operation::Write(&[cmd]), Operation::Write(data)],
+                )
+                .await
+            }
+            (true, false) => {
+                i2c.transaction(
+                    GENERAL_CALL_ADDR,
+                    &mut [
+                        Operation::Write(&[cmd]),
+                        Operation::Write(data),
+                        Operation::Write(&pec_byte),
+                    ],
+                )
+                .await
+            }
+        };
+        // Any byte following the command prevents pinning a NACK on the command alone
+        payload_res_map(res, if pec { &pec_byte } else { data })
     }
 
-    /// Destroy this driver instance and return the underlying I2C bus instance.
-    pub fn destroy(self) -> I2C {
-        self.i2c
+    // Resolve a NACK of unknown source by probing the general call address
+    pub(crate) async fn resolve_unknown_nack<I2C: I2c>(
+        i2c: &mut I2C,
+        res: Result<(), Error<I2C::Error>>,
+        has_data: bool,
+    ) -> Result<(), Error<I2C::Error>> {
+        match res {
+            Err(Error::NoAck) => match probe_addr(i2c, GENERAL_CALL_ADDR).await {
+                Ok(false) => Err(Error::NoAckCall),
+                Ok(true) if !has_data => Err(Error::NoAckCmd),
+                Ok(true) => Err(Error::NoAckData),
+                Err(_) => Err(Error::NoAck),
+            },
+            res => res,
+        }
     }
 
-    /// Issue a reset command general call, which instructs devices on the bus to latch their addresses
-    /// and reset their registers to the default state.
-    ///
-    /// If successful, then at least one device on the bus ACKed the reset command.
-    /// However, there is no guarantee that it actually performed a reset. One should verify
-    /// the device actually honors reset general calls with the datasheet.
-    ///
-    /// # Errors
-    ///
-    /// If no device accepts general calls, [`Error::NoAckCall`] will be returned.
+    /// Extension trait for issuing general calls directly on any I2C bus, without moving it into
+    /// a [`GeneralCall`] driver.
     ///
-    /// If at least one device accepts general calls but not a reset command, [`Error::NoAckCmd`] will be returned.
-    ///
-    /// If any other I2C error occurs, the underlying error will be returned.
-    pub async fn reset(&mut self) -> Result<(), Error<E>> {
-        let res = self
-            .i2c
-            .write(GENERAL_CALL_ADDR, &[Command::Reset.into()])
-            .await;
-        Self::res_map(res)
+    /// See the equivalent [`GeneralCall`] methods for details on each call and its errors.
+    #[allow(async_fn_in_trait)]
+    pub trait GeneralCallExt: I2c {
+        /// Probe whether any device listens to general calls. See [`GeneralCall::probe`].
+        async fn general_call_probe(&mut self) -> Result<bool, Error<Self::Error>>;
+
+        /// Issue a reset command general call. See [`GeneralCall::reset`].
+        async fn general_call_reset(&mut self) -> Result<(), Error<Self::Error>>;
+
+        /// Issue an address latch command general call. See [`GeneralCall::latch_addr`].
+        async fn general_call_latch_addr(&mut self) -> Result<(), Error<Self::Error>>;
+
+        /// Issue an arbitrary command general call. See [`GeneralCall::call`].
+        async fn general_call(&mut self, cmd: NonZeroU8) -> Result<(), Error<Self::Error>>;
+
+        /// Issue an arbitrary command general call followed by data bytes.
+        /// See [`GeneralCall::call_with_data`].
+        async fn general_call_with_data(
+            &mut self,
+            cmd: NonZeroU8,
+            data: &[u8],
+        ) -> Result<(), Error<Self::Error>>;
+
+        /// Issue a hardware general call. See [`GeneralCall::hardware_call`].
+        async fn hardware_general_call(
+            &mut self,
+            master_addr: u8,
+            data: &[u8],
+        ) -> Result<(), Error<Self::Error>>;
     }
 
-    /// Issue a address latch command general call, which instructs devices on the bus to latch their addresses
-    /// but NOT perform a full reset.
-    ///
-    /// If successful, then at least one device on the bus ACKed the address latch command.
-    /// However, there is no guarantee that it actually performed a latch. One should verify
-    /// the device actually honors address latch general calls with the datasheet.
-    ///
-    /// # Errors
-    ///
-    /// If no device accepts general calls, [`Error::NoAckCall`] will be returned.
-    ///
-    /// If at least one device accepts general calls but not an address latch command,
-    /// [`Error::NoAckCmd`] will be returned.
-    ///
-    /// If any other I2C error occurs, the underlying error will be returned.
-    pub async fn latch_addr(&mut self) -> Result<(), Error<E>> {
-        let res = self
-            .i2c
-            .write(GENERAL_CALL_ADDR, &[Command::LatchAddr.into()])
-            .await;
-        Self::res_map(res)
+    impl<I2C: I2c> GeneralCallExt for I2C {
+        async fn general_call_probe(&mut self) -> Result<bool, Error<Self::Error>> {
+            probe_addr(self, GENERAL_CALL_ADDR).await
+        }
+
+        async fn general_call_reset(&mut self) -> Result<(), Error<Self::Error>> {
+            let res = self
+                .write(GENERAL_CALL_ADDR, &[Command::Reset.into()])
+                .await;
+            res_map(res)
+        }
+
+        async fn general_call_latch_addr(&mut self) -> Result<(), Error<Self::Error>> {
+            let res = self
+                .write(GENERAL_CALL_ADDR, &[Command::LatchAddr.into()])
+                .await;
+            res_map(res)
+        }
+
+        async fn general_call(&mut self, cmd: NonZeroU8) -> Result<(), Error<Self::Error>> {
+            let res = self.write(GENERAL_CALL_ADDR, &[cmd.get()]).await;
+            res_map(res)
+        }
+
+        async fn general_call_with_data(
+            &mut self,
+            cmd: NonZeroU8,
+            data: &[u8],
+        ) -> Result<(), Error<Self::Error>> {
+            write_general_call(self, false, software_call_byte(cmd)?, data).await
+        }
+
+        async fn hardware_general_call(
+            &mut self,
+            master_addr: u8,
+            data: &[u8],
+        ) -> Result<(), Error<Self::Error>> {
+            let addr_byte = [hardware_call_byte(master_addr)?];
+            // Some HALs reject zero-length transfers, so an empty payload is left out
+            let res = if data.is_empty() {
+                self.write(GENERAL_CALL_ADDR, &addr_byte).await
+            } else {
+                self.transaction(
+                    GENERAL_CALL_ADDR,
+                    &mut [Operation::Write(&addr_byte), Operation::Write(data)],
+                )
+                .await
+            };
+            payload_res_map(res, data)
+        }
     }
 
-    /// Issue an arbitrary command general call. The command code must be nonzero as that is
-    /// forbidden by spec.
-    ///
-    /// If successful, then at least one device on the bus ACKed the command.
-    /// However, there is no guarantee that it actually performed the command. One should verify
-    /// the device actually honors the command with the datasheet.
-    ///
-    /// # Errors
-    ///
-    /// If no device accepts general calls, [`Error::NoAckCall`] will be returned.
+    /// I2C general call driver.
     ///
-    /// If at least one device accepts general calls but not the command,
-    /// [`Error::NoAckCmd`] will be returned.
-    ///
-    /// If any other I2C error occurs, the underlying error will be returned.
-    pub async fn call(&mut self, cmd: core::num::NonZeroU8) -> Result<(), Error<E>> {
-        let res = self.i2c.write(GENERAL_CALL_ADDR, &[cmd.get()]).await;
-        Self::res_map(res)
+    /// Optionally owns a delay provider `D`, which enables waiting for devices to settle after a
+    /// reset or address latch (see [`GeneralCall::with_delay`]).
+    pub struct GeneralCall<I2C: I2c, D = ()> {
+        pub(crate) i2c: I2C,
+        pub(crate) delay: D,
+        settle_time_us: u32,
+        pub(crate) resolve_unknown_nack: bool,
+        pub(crate) pec: bool,
+    }
+
+    impl<I2C: I2c> GeneralCall<I2C> {
+        /// Create a new instance of an I2C general call driver.
+        pub fn new(i2c: I2C) -> Self {
+            Self {
+                i2c,
+                delay: (),
+                settle_time_us: DEFAULT_SETTLE_TIME_US,
+                resolve_unknown_nack: false,
+                pec: false,
+            }
+        }
+    }
+
+    impl<I2C: I2c, D: DelayNs> GeneralCall<I2C, D> {
+        /// Create a new instance of an I2C general call driver which owns a delay provider, used to
+        /// wait for devices to settle after a reset or address latch.
+        ///
+        /// The settle time defaults to [`DEFAULT_SETTLE_TIME_US`].
+        pub fn with_delay(i2c: I2C, delay: D) -> Self {
+            Self {
+                i2c,
+                delay,
+                settle_time_us: DEFAULT_SETTLE_TIME_US,
+                resolve_unknown_nack: false,
+                pec: false,
+            }
+        }
+
+        /// Destroy this driver instance and return the underlying I2C bus and delay instances.
+        pub fn destroy_with_delay(self) -> (I2C, D) {
+            (self.i2c, self.delay)
+        }
+
+        /// Set the time, in microseconds, to wait for devices to settle after a reset or address
+        /// latch issued by [`Self::reset_and_wait`] or [`Self::latch_addr_and_wait`].
+        pub fn set_settle_time_us(&mut self, settle_time_us: u32) {
+            self.settle_time_us = settle_time_us;
+        }
+
+        /// Get the time, in microseconds, waited for devices to settle after a reset or address
+        /// latch.
+        pub fn settle_time_us(&self) -> u32 {
+            self.settle_time_us
+        }
+
+        /// Issue a reset command general call, then wait the configured settle time so devices are
+        /// ready to respond again once this returns.
+        ///
+        /// # Errors
+        ///
+        /// Same as [`Self::reset`]. No delay is performed if the general call fails.
+        pub async fn reset_and_wait(&mut self) -> Result<(), Error<I2C::Error>> {
+            self.reset_and_wait_us(self.settle_time_us).await
+        }
+
+        /// Issue a reset command general call, then wait `settle_time_us` microseconds instead of
+        /// the configured settle time.
+        ///
+        /// # Errors
+        ///
+        /// Same as [`Self::reset`]. No delay is performed if the general call fails.
+        pub async fn reset_and_wait_us(
+            &mut self,
+            settle_time_us: u32,
+        ) -> Result<(), Error<I2C::Error>> {
+            self.reset().await?;
+            self.delay.delay_us(settle_time_us).await;
+            Ok(())
+        }
+
+        /// Issue an address latch command general call, then wait the configured settle time so
+        /// devices are ready to respond again once this returns.
+        ///
+        /// # Errors
+        ///
+        /// Same as [`Self::latch_addr`]. No delay is performed if the general call fails.
+        pub async fn latch_addr_and_wait(&mut self) -> Result<(), Error<I2C::Error>> {
+            self.latch_addr_and_wait_us(self.settle_time_us).await
+        }
+
+        /// Issue an address latch command general call, then wait `settle_time_us` microseconds
+        /// instead of the configured settle time.
+        ///
+        /// # Errors
+        ///
+        /// Same as [`Self::latch_addr`]. No delay is performed if the general call fails.
+        pub async fn latch_addr_and_wait_us(
+            &mut self,
+            settle_time_us: u32,
+        ) -> Result<(), Error<I2C::Error>> {
+            self.latch_addr().await?;
+            self.delay.delay_us(settle_time_us).await;
+            Ok(())
+        }
+    }
+
+    impl<E: i2c::Error, I2C: I2c<Error = E>, D> GeneralCall<I2C, D> {
+        /// Destroy this driver instance and return the underlying I2C bus instance.
+        pub fn destroy(self) -> I2C {
+            self.i2c
+        }
+
+        /// Probe whether any device on the bus listens to general calls, by sending only the
+        /// general call address without a command byte.
+        ///
+        /// Returns `true` if at least one device ACKed the general call address. Devices which
+        /// acknowledge general calls must wait for the command byte before acting, so a
+        /// general call without one is ignored by compliant devices. Consult the datasheet of
+        /// any device that is not fully compliant.
+        ///
+        /// # Errors
+        ///
+        /// If any I2C error other than a NACK occurs, the underlying error will be returned.
+        pub async fn probe(&mut self) -> Result<bool, Error<E>> {
+            self.i2c.general_call_probe().await
+        }
+
+        /// Enable or disable resolving NACKs of unknown source (as reported by HALs which cannot
+        /// tell an address NACK from a data NACK). Disabled by default.
+        ///
+        /// When enabled, a general call failing with a NACK of unknown source is followed by a
+        /// [`Self::probe`] to determine whether [`Error::NoAckCall`] or [`Error::NoAckCmd`] (or
+        /// [`Error::NoAckData`] if data followed the command) should be returned. When disabled,
+        /// or if the probe itself fails, [`Error::NoAck`] is returned.
+        pub fn set_resolve_unknown_nack(&mut self, enable: bool) {
+            self.resolve_unknown_nack = enable;
+        }
+
+        /// Enable or disable SMBus Packet Error Checking on software general calls. Disabled by
+        /// default.
+        ///
+        /// When enabled, [`Self::reset`], [`Self::latch_addr`], [`Self::call`] and
+        /// [`Self::call_with_data`] append a PEC byte computed over the general call write address
+        /// and every byte sent after it, as required by SMBus devices listening to general calls
+        /// with PEC. Since the PEC byte follows the command, a refused command is then reported as
+        /// [`Error::NoAckData`].
+        pub fn set_pec(&mut self, enable: bool) {
+            self.pec = enable;
+        }
+
+        // Write a software general call command and data, honoring PEC mode
+        pub(crate) async fn write_cmd(&mut self, cmd: u8, data: &[u8]) -> Result<(), Error<E>> {
+            write_general_call(&mut self.i2c, self.pec, cmd, data).await
+        }
+
+        // Resolve a NACK of unknown source if enabled, see `set_resolve_unknown_nack`
+        pub(crate) async fn resolve_nack(
+            &mut self,
+            res: Result<(), Error<E>>,
+            has_data: bool,
+        ) -> Result<(), Error<E>> {
+            if self.resolve_unknown_nack {
+                resolve_unknown_nack(&mut self.i2c, res, has_data).await
+            } else {
+                res
+            }
+        }
+
+        /// Issue a reset command general call, which instructs devices on the bus to latch their addresses
+        /// and reset their registers to the default state.
+        ///
+        /// If successful, then at least one device on the bus ACKed the reset command.
+        /// However, there is no guarantee that it actually performed a reset. One should verify
+        /// the device actually honors reset general calls with the datasheet.
+        ///
+        /// # Errors
+        ///
+        /// If no device accepts general calls, [`Error::NoAckCall`] will be returned.
+        ///
+        /// If at least one device accepts general calls but not a reset command, [`Error::NoAckCmd`] will be returned.
+        ///
+        /// If the HAL cannot tell whether the address or a data byte was refused, [`Error::NoAck`]
+        /// will be returned unless [`Self::set_resolve_unknown_nack`] is enabled.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn reset(&mut self) -> Result<(), Error<E>> {
+            let res = self.write_cmd(Command::Reset.into(), &[]).await;
+            self.resolve_nack(res, self.pec).await
+        }
+
+        /// Issue a address latch command general call, which instructs devices on the bus to latch their addresses
+        /// but NOT perform a full reset.
+        ///
+        /// If successful, then at least one device on the bus ACKed the address latch command.
+        /// However, there is no guarantee that it actually performed a latch. One should verify
+        /// the device actually honors address latch general calls with the datasheet.
+        ///
+        /// # Errors
+        ///
+        /// If no device accepts general calls, [`Error::NoAckCall`] will be returned.
+        ///
+        /// If at least one device accepts general calls but not an address latch command,
+        /// [`Error::NoAckCmd`] will be returned.
+        ///
+        /// If the HAL cannot tell whether the address or a data byte was refused, [`Error::NoAck`]
+        /// will be returned unless [`Self::set_resolve_unknown_nack`] is enabled.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn latch_addr(&mut self) -> Result<(), Error<E>> {
+            let res = self.write_cmd(Command::LatchAddr.into(), &[]).await;
+            self.resolve_nack(res, self.pec).await
+        }
+
+        /// Issue an arbitrary command general call. The command code must be nonzero as that is
+        /// forbidden by spec.
+        ///
+        /// If successful, then at least one device on the bus ACKed the command.
+        /// However, there is no guarantee that it actually performed the command. One should verify
+        /// the device actually honors the command with the datasheet.
+        ///
+        /// # Errors
+        ///
+        /// If no device accepts general calls, [`Error::NoAckCall`] will be returned.
+        ///
+        /// If at least one device accepts general calls but not the command,
+        /// [`Error::NoAckCmd`] will be returned.
+        ///
+        /// If the HAL cannot tell whether the address or a data byte was refused, [`Error::NoAck`]
+        /// will be returned unless [`Self::set_resolve_unknown_nack`] is enabled.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn call(&mut self, cmd: NonZeroU8) -> Result<(), Error<E>> {
+            let res = self.write_cmd(cmd.get(), &[]).await;
+            self.resolve_nack(res, self.pec).await
+        }
+
+        /// Issue an arbitrary command general call followed by additional data bytes in the same
+        /// transaction. This is needed by devices whose general call commands carry arguments.
+        ///
+        /// The command code must be nonzero as that is forbidden by spec, and its LSB must be clear
+        /// as a set LSB denotes a hardware general call (see [`Self::hardware_call`]).
+        ///
+        /// If successful, then at least one device on the bus ACKed the command and all data bytes.
+        /// However, there is no guarantee that it actually performed the command. One should verify
+        /// the device actually honors the command with the datasheet.
+        ///
+        /// # Errors
+        ///
+        /// If the command code has its LSB set, [`Error::InvalidCmd`] will be returned and nothing
+        /// is sent on the bus.
+        ///
+        /// If no device accepts general calls, [`Error::NoAckCall`] will be returned.
+        ///
+        /// If at least one device accepts general calls but not the command, [`Error::NoAckCmd`]
+        /// will be returned when `data` is empty. Otherwise, [`Error::NoAckData`] will be returned
+        /// whether the command byte or any data byte was refused: `embedded-hal` does not report
+        /// which byte was refused, so the position of the NACK within the payload is not available.
+        ///
+        /// If the HAL cannot tell whether the address or a data byte was refused, [`Error::NoAck`]
+        /// will be returned unless [`Self::set_resolve_unknown_nack`] is enabled.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn call_with_data(
+            &mut self,
+            cmd: NonZeroU8,
+            data: &[u8],
+        ) -> Result<(), Error<E>> {
+            let res = self.write_cmd(software_call_byte(cmd)?, data).await;
+            self.resolve_nack(res, self.pec || !data.is_empty()).await
+        }
+
+        /// Issue a hardware general call, which announces this master's 7-bit address to the bus
+        /// followed by arbitrary data bytes.
+        ///
+        /// This is typically used by a hardware master (such as a keyboard scanner) that cannot be
+        /// programmed with the address of the target it wishes to talk to. Interested devices
+        /// acknowledge the address byte and are expected to accept the data that follows.
+        ///
+        /// # Errors
+        ///
+        /// If `master_addr` is not a valid 7-bit address or is reserved (see
+        /// [`reserved::is_target_addr`](crate::reserved::is_target_addr)), [`Error::InvalidAddr`]
+        /// will be returned and nothing is sent on the bus.
+        ///
+        /// If no device accepts general calls, [`Error::NoAckCall`] will be returned.
+        ///
+        /// If at least one device accepts general calls but rejects the master address,
+        /// [`Error::NoAckCmd`] will be returned when `data` is empty, otherwise
+        /// [`Error::NoAckData`] will be returned as the address byte and data bytes cannot be told
+        /// apart.
+        ///
+        /// If the HAL cannot tell whether the address or a data byte was refused, [`Error::NoAck`]
+        /// will be returned unless [`Self::set_resolve_unknown_nack`] is enabled.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn hardware_call(
+            &mut self,
+            master_addr: u8,
+            data: &[u8],
+        ) -> Result<(), Error<E>> {
+            let res = self.i2c.hardware_general_call(master_addr, data).await;
+            self.resolve_nack(res, !data.is_empty()).await
+        }
     }
 }
@@ -0,0 +1,104 @@
+//! SMBus Packet Error Checking (PEC).
+//!
+//! PEC is a CRC-8 with polynomial `x^8 + x^2 + x + 1` (0x07) and no reflection, computed over
+//! every byte of a transaction including the address bytes.
+
+// CRC-8 lookup table, generated at compile time
+const TABLE: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Incremental SMBus PEC calculator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Pec(u8);
+
+impl Pec {
+    /// Start a new PEC calculation.
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Feed bytes into the calculation.
+    pub fn update(&mut self, bytes: &[u8]) -> &mut Self {
+        for &byte in bytes {
+            self.0 = TABLE[usize::from(self.0 ^ byte)];
+        }
+        self
+    }
+
+    /// Get the PEC of all bytes fed so far.
+    pub const fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Compute the PEC of the given bytes.
+pub fn pec(bytes: &[u8]) -> u8 {
+    Pec::new().update(bytes).value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pec, pec};
+
+    // Bitwise CRC-8 the table is checked against
+    fn bitwise(bytes: &[u8]) -> u8 {
+        let mut crc = 0u8;
+        for &byte in bytes {
+            crc ^= byte;
+            for _ in 0..8 {
+                crc = if crc & 0x80 != 0 {
+                    (crc << 1) ^ 0x07
+                } else {
+                    crc << 1
+                };
+            }
+        }
+        crc
+    }
+
+    #[test]
+    fn check_value() {
+        assert_eq!(pec(b"123456789"), 0xF4);
+    }
+
+    #[test]
+    fn smbus_frames() {
+        // Prepare to ARP: device default address (write), command
+        assert_eq!(pec(&[0xC2, 0x01]), 0xC0);
+        // Reset general call: general call address (write), command
+        assert_eq!(pec(&[0x00, 0x06]), 0x12);
+    }
+
+    #[test]
+    fn table_matches_bitwise() {
+        for byte in 0..=u8::MAX {
+            assert_eq!(pec(&[byte]), bitwise(&[byte]));
+            assert_eq!(pec(&[0xA5, byte]), bitwise(&[0xA5, byte]));
+        }
+    }
+
+    #[test]
+    fn incremental_update() {
+        let value = Pec::new().update(b"1234").update(b"56789").value();
+        assert_eq!(value, pec(b"123456789"));
+        assert_eq!(Pec::new().value(), 0);
+    }
+}
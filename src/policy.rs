@@ -0,0 +1,323 @@
+//! Escalating recovery policy for unresponsive devices.
+//!
+//! A [`RecoveryPolicy`] tries progressively harsher remedies until every expected device
+//! responds again:
+//!
+//! 1. A general call reset, followed by presence verification.
+//! 2. SCL/SDA bus recovery (see [`crate::recovery`]), followed by another reset and verification.
+//! 3. Toggling a power-enable pin, followed by presence verification.
+//!
+//! Stages 2 and 3 are only run if configured.
+
+use core::convert::Infallible;
+
+use embedded_hal::digital::{ErrorType, OutputPin};
+
+use crate::Error;
+use crate::verify::{VerifyConfig, VerifyReport};
+
+pub use blocking::{BusRecoveryStage, RecoverBus, RecoveryPolicy};
+
+/// Default time, in microseconds, power is removed during a power cycle.
+pub const DEFAULT_POWER_OFF_US: u32 = 100_000;
+
+/// Default time, in microseconds, to wait for devices to power up after a power cycle.
+pub const DEFAULT_POWER_ON_US: u32 = 100_000;
+
+/// Stage of a [`RecoveryPolicy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecoveryStage {
+    /// General call reset.
+    GeneralCallReset,
+    /// SCL/SDA bus recovery, followed by a general call reset.
+    BusRecovery,
+    /// Power cycle.
+    PowerCycle,
+}
+
+/// Error encountered while running a stage of a [`RecoveryPolicy`].
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StageError<E, RE, PE> {
+    /// General call or verification error.
+    GeneralCall(Error<E>),
+    /// Bus recovery error.
+    BusRecovery(RE),
+    /// Power-enable pin error.
+    PowerCycle(PE),
+}
+
+/// Outcome of running a [`RecoveryPolicy`].
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RecoveryOutcome<E, RE, PE> {
+    /// Stage after which every expected device responded, or `None` if all stages failed.
+    pub recovered_by: Option<RecoveryStage>,
+    /// Last stage that was attempted.
+    pub last_stage: RecoveryStage,
+    /// Presence report from the most recent successful verification.
+    pub report: VerifyReport,
+    /// Most recent error encountered while running a stage, if any.
+    pub last_error: Option<StageError<E, RE, PE>>,
+}
+
+impl<E, RE, PE> RecoveryOutcome<E, RE, PE> {
+    /// Check if every expected device responded after one of the stages.
+    pub fn is_recovered(&self) -> bool {
+        self.recovered_by.is_some()
+    }
+}
+
+/// Placeholder for a [`RecoveryPolicy`] stage that is not configured.
+#[derive(Clone, Copy, Debug)]
+pub enum NoStage {}
+
+impl ErrorType for NoStage {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoStage {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        match *self {}
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        match *self {}
+    }
+}
+
+// Power-enable pin along with its timing
+struct PowerCycle<P> {
+    pin: P,
+    off_us: u32,
+    on_us: u32,
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        "doc = \"Blocking recovery policy.\"",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch", "doc = \"Async recovery policy.\"")
+)]
+pub mod asynch {
+    use embedded_hal::digital::{InputPin, OutputPin};
+    use embedded_hal_async::delay::DelayNs;
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{
+        DEFAULT_POWER_OFF_US, DEFAULT_POWER_ON_US, NoStage, PowerCycle, RecoveryOutcome,
+        RecoveryStage, StageError, VerifyConfig, VerifyReport,
+    };
+    use crate::Error;
+    use crate::asynch::GeneralCall;
+    use crate::recovery::RecoveryError;
+    use crate::recovery::asynch::BusRecovery;
+
+    /// SCL/SDA bus recovery stage of a [`RecoveryPolicy`].
+    ///
+    /// While the I2C peripheral owns the SCL and SDA pins, it keeps driving them, so toggling them
+    /// as GPIOs has no effect. Implementations must therefore take the pins away from the
+    /// peripheral before recovering the bus, and hand them back to it afterwards, even if recovery
+    /// failed. [`BusRecoveryStage`] does so around a [`BusRecovery`] with user-provided closures.
+    #[allow(async_fn_in_trait)]
+    pub trait RecoverBus<I2C> {
+        /// Bus recovery error.
+        type Error;
+
+        /// Recover the bus used by `i2c`.
+        async fn recover_bus(&mut self, i2c: &mut I2C) -> Result<(), Self::Error>;
+    }
+
+    impl<I2C> RecoverBus<I2C> for NoStage {
+        type Error = core::convert::Infallible;
+
+        async fn recover_bus(&mut self, _i2c: &mut I2C) -> Result<(), Self::Error> {
+            match *self {}
+        }
+    }
+
+    /// [`RecoverBus`] stage performing [`BusRecovery`] between handing the pins over from and back
+    /// to the I2C peripheral.
+    pub struct BusRecoveryStage<SCL, SDA, D, F, G> {
+        recovery: BusRecovery<SCL, SDA, D>,
+        release: F,
+        reacquire: G,
+    }
+
+    impl<SCL, SDA, D, F, G> BusRecoveryStage<SCL, SDA, D, F, G> {
+        /// Create a new bus recovery stage.
+        ///
+        /// `release` is called before recovery and must switch the SCL and SDA pins from the I2C
+        /// peripheral to GPIO mode, so `recovery` can drive them. `reacquire` is called after
+        /// recovery, whether it succeeded or not, and must hand the pins back to the peripheral.
+        pub fn new(recovery: BusRecovery<SCL, SDA, D>, release: F, reacquire: G) -> Self {
+            Self {
+                recovery,
+                release,
+                reacquire,
+            }
+        }
+
+        /// Destroy this stage and return the bus recovery and the pin handover closures.
+        pub fn destroy(self) -> (BusRecovery<SCL, SDA, D>, F, G) {
+            (self.recovery, self.release, self.reacquire)
+        }
+    }
+
+    impl<I2C, E, SCL, SDA, D, F, G> RecoverBus<I2C> for BusRecoveryStage<SCL, SDA, D, F, G>
+    where
+        SCL: OutputPin<Error = E>,
+        SDA: OutputPin<Error = E> + InputPin<Error = E>,
+        D: DelayNs,
+        F: FnMut(&mut I2C),
+        G: FnMut(&mut I2C),
+    {
+        type Error = RecoveryError<E>;
+
+        async fn recover_bus(&mut self, i2c: &mut I2C) -> Result<(), Self::Error> {
+            (self.release)(i2c);
+            let res = self.recovery.recover().await;
+            (self.reacquire)(i2c);
+            res.map(|_| ())
+        }
+    }
+
+    /// Escalating recovery policy. See the [module level documentation](super) for the stages.
+    pub struct RecoveryPolicy<R = NoStage, P = NoStage> {
+        verify: VerifyConfig,
+        bus_recovery: Option<R>,
+        power_cycle: Option<PowerCycle<P>>,
+    }
+
+    impl RecoveryPolicy {
+        /// Create a new recovery policy which only performs a general call reset, verifying
+        /// presence of devices with `verify`.
+        pub fn new(verify: VerifyConfig) -> Self {
+            Self {
+                verify,
+                bus_recovery: None,
+                power_cycle: None,
+            }
+        }
+    }
+
+    impl<R, P: OutputPin> RecoveryPolicy<R, P> {
+        /// Add an SCL/SDA bus recovery stage, run if the general call reset does not bring back
+        /// every expected device.
+        pub fn with_bus_recovery<R2>(self, bus_recovery: R2) -> RecoveryPolicy<R2, P> {
+            RecoveryPolicy {
+                verify: self.verify,
+                bus_recovery: Some(bus_recovery),
+                power_cycle: self.power_cycle,
+            }
+        }
+
+        /// Add a power cycle stage as a last resort, which drives the active-high power-enable
+        /// pin low, waits [`DEFAULT_POWER_OFF_US`], drives it high again and waits
+        /// [`DEFAULT_POWER_ON_US`] for devices to power up.
+        pub fn with_power_cycle<P2: OutputPin>(self, power_enable: P2) -> RecoveryPolicy<R, P2> {
+            RecoveryPolicy {
+                verify: self.verify,
+                bus_recovery: self.bus_recovery,
+                power_cycle: Some(PowerCycle {
+                    pin: power_enable,
+                    off_us: DEFAULT_POWER_OFF_US,
+                    on_us: DEFAULT_POWER_ON_US,
+                }),
+            }
+        }
+
+        /// Set the time, in microseconds, power is removed and then allowed to settle during the
+        /// power cycle stage. Has no effect if no power cycle stage is configured.
+        pub fn set_power_cycle_time_us(&mut self, off_us: u32, on_us: u32) {
+            if let Some(power_cycle) = &mut self.power_cycle {
+                power_cycle.off_us = off_us;
+                power_cycle.on_us = on_us;
+            }
+        }
+
+        /// Destroy this policy and return the bus recovery stage and power-enable pin, if
+        /// configured.
+        pub fn destroy(self) -> (Option<R>, Option<P>) {
+            (self.bus_recovery, self.power_cycle.map(|p| p.pin))
+        }
+
+        /// Run each configured stage in order until every device in `expected` responds.
+        ///
+        /// Errors encountered in a stage do not abort the policy. Instead, the next stage is
+        /// attempted and the error is recorded in the returned outcome.
+        pub async fn run<I2C: I2c, D: DelayNs>(
+            &mut self,
+            general_call: &mut GeneralCall<I2C, D>,
+            expected: &[u8],
+        ) -> RecoveryOutcome<I2C::Error, R::Error, P::Error>
+        where
+            R: RecoverBus<I2C>,
+        {
+            let mut outcome = RecoveryOutcome {
+                recovered_by: None,
+                last_stage: RecoveryStage::GeneralCallReset,
+                report: Default::default(),
+                last_error: None,
+            };
+
+            let res = general_call.reset_and_verify(expected, &self.verify).await;
+            if Self::record(&mut outcome, res) {
+                return outcome;
+            }
+
+            if let Some(bus_recovery) = &mut self.bus_recovery {
+                outcome.last_stage = RecoveryStage::BusRecovery;
+                match bus_recovery.recover_bus(&mut general_call.i2c).await {
+                    Ok(()) => {
+                        let res = general_call.reset_and_verify(expected, &self.verify).await;
+                        if Self::record(&mut outcome, res) {
+                            return outcome;
+                        }
+                    }
+                    Err(e) => outcome.last_error = Some(StageError::BusRecovery(e)),
+                }
+            }
+
+            if let Some(power_cycle) = &mut self.power_cycle {
+                outcome.last_stage = RecoveryStage::PowerCycle;
+                if let Err(e) = power_cycle.pin.set_low() {
+                    outcome.last_error = Some(StageError::PowerCycle(e));
+                    return outcome;
+                }
+                general_call.delay.delay_us(power_cycle.off_us).await;
+                if let Err(e) = power_cycle.pin.set_high() {
+                    outcome.last_error = Some(StageError::PowerCycle(e));
+                    return outcome;
+                }
+                general_call.delay.delay_us(power_cycle.on_us).await;
+
+                let res = general_call.verify_presence(expected, &self.verify).await;
+                Self::record(&mut outcome, res);
+            }
+
+            outcome
+        }
+
+        // Record the result of a verification, returning whether every expected device responded
+        fn record<E, RE, PE>(
+            outcome: &mut RecoveryOutcome<E, RE, PE>,
+            res: Result<VerifyReport, Error<E>>,
+        ) -> bool {
+            match res {
+                Ok(report) => {
+                    outcome.report = report;
+                    if report.missing.is_empty() {
+                        outcome.recovered_by = Some(outcome.last_stage);
+                    }
+                }
+                Err(e) => outcome.last_error = Some(StageError::GeneralCall(e)),
+            }
+
+            outcome.recovered_by.is_some()
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! I2C bus recovery by bit-banging SCL and SDA.
+//!
+//! When a target is interrupted mid-transfer it may hold SDA low indefinitely, preventing any
+//! master (including a general call) from using the bus. Clocking SCL until the target releases
+//! SDA and then generating a STOP condition returns the bus to an idle state, after which the pins
+//! can be handed back to the I2C peripheral.
+
+pub use blocking::BusRecovery;
+
+/// Maximum number of SCL pulses needed for a target to finish shifting out a byte and its ACK.
+pub const MAX_RECOVERY_PULSES: u8 = 9;
+
+/// Default half period of the recovery clock in microseconds (corresponds to 100 kHz).
+pub const DEFAULT_HALF_PERIOD_US: u32 = 5;
+
+/// I2C bus recovery error.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecoveryError<E> {
+    /// SDA was still held low after clocking out the maximum number of pulses and a STOP.
+    SdaStuckLow,
+    /// Other GPIO error was encountered.
+    Pin(E),
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        "doc = \"Blocking bus recovery using an [`embedded_hal::delay::DelayNs`] delay.\"",
+        idents(embedded_hal_async(sync = "embedded_hal"))
+    ),
+    async(
+        feature = "async",
+        self = "asynch",
+        "doc = \"Async bus recovery using an [`embedded_hal_async::delay::DelayNs`] delay.\""
+    )
+)]
+pub mod asynch {
+    use embedded_hal::digital::{InputPin, OutputPin};
+    use embedded_hal_async::delay::DelayNs;
+
+    use super::{DEFAULT_HALF_PERIOD_US, MAX_RECOVERY_PULSES, RecoveryError};
+
+    /// I2C bus recovery driver.
+    ///
+    /// The SCL and SDA pins are expected to be configured as open-drain outputs, such that setting
+    /// them high releases the line. SDA must also be readable to detect when it is released.
+    pub struct BusRecovery<SCL, SDA, D> {
+        scl: SCL,
+        sda: SDA,
+        delay: D,
+        half_period_us: u32,
+    }
+
+    impl<E, SCL, SDA, D> BusRecovery<SCL, SDA, D>
+    where
+        SCL: OutputPin<Error = E>,
+        SDA: OutputPin<Error = E> + InputPin<Error = E>,
+        D: DelayNs,
+    {
+        /// Create a new instance of an I2C bus recovery driver.
+        ///
+        /// The recovery clock half period defaults to [`DEFAULT_HALF_PERIOD_US`].
+        pub fn new(scl: SCL, sda: SDA, delay: D) -> Self {
+            Self {
+                scl,
+                sda,
+                delay,
+                half_period_us: DEFAULT_HALF_PERIOD_US,
+            }
+        }
+
+        /// Destroy this driver instance and return the SCL pin, SDA pin and delay instances, so the
+        /// pins can be handed back to the I2C peripheral.
+        pub fn destroy(self) -> (SCL, SDA, D) {
+            (self.scl, self.sda, self.delay)
+        }
+
+        /// Set the half period of the recovery clock in microseconds.
+        pub fn set_half_period_us(&mut self, half_period_us: u32) {
+            self.half_period_us = half_period_us;
+        }
+
+        /// Attempt to recover the bus by clocking SCL until SDA is released (up to
+        /// [`MAX_RECOVERY_PULSES`] pulses), then generating a STOP condition.
+        ///
+        /// Returns the number of SCL pulses that were needed.
+        ///
+        /// # Errors
+        ///
+        /// If SDA is still held low after recovery, [`RecoveryError::SdaStuckLow`] will be returned.
+        ///
+        /// If any GPIO error occurs, the underlying error will be returned.
+        pub async fn recover(&mut self) -> Result<u8, RecoveryError<E>> {
+            self.sda.set_high().map_err(RecoveryError::Pin)?;
+            self.scl.set_high().map_err(RecoveryError::Pin)?;
+            self.delay.delay_us(self.half_period_us).await;
+
+            let mut pulses = 0;
+            while pulses < MAX_RECOVERY_PULSES && self.sda.is_low().map_err(RecoveryError::Pin)? {
+                self.scl.set_low().map_err(RecoveryError::Pin)?;
+                self.delay.delay_us(self.half_period_us).await;
+                self.scl.set_high().map_err(RecoveryError::Pin)?;
+                self.delay.delay_us(self.half_period_us).await;
+                pulses += 1;
+            }
+
+            // STOP condition: SDA rising while SCL is high
+            self.scl.set_low().map_err(RecoveryError::Pin)?;
+            self.delay.delay_us(self.half_period_us).await;
+            self.sda.set_low().map_err(RecoveryError::Pin)?;
+            self.delay.delay_us(self.half_period_us).await;
+            self.scl.set_high().map_err(RecoveryError::Pin)?;
+            self.delay.delay_us(self.half_period_us).await;
+            self.sda.set_high().map_err(RecoveryError::Pin)?;
+            self.delay.delay_us(self.half_period_us).await;
+
+            if self.sda.is_low().map_err(RecoveryError::Pin)? {
+                Err(RecoveryError::SdaStuckLow)
+            } else {
+                Ok(pulses)
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Reserved 7-bit I2C addresses.
+//!
+//! The I2C specification reserves the 7-bit addresses `0000 XXX` and `1111 XXX` for special
+//! purposes, leaving `0x08..=0x77` for targets.
+
+use core::ops::RangeInclusive;
+
+/// A reserved 7-bit I2C address range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReservedAddress {
+    /// `0000 000`: general call address when writing, START byte when reading.
+    GeneralCall,
+    /// `0000 001`: CBUS address.
+    Cbus,
+    /// `0000 010`: reserved for a different bus format.
+    DifferentBusFormat,
+    /// `0000 011`: reserved for future purposes.
+    FuturePurposes,
+    /// `0000 1XX`: Hs-mode master codes.
+    HsMasterCode,
+    /// `1111 0XX`: 10-bit target addressing prefix.
+    TenBitPrefix,
+    /// `1111 1XX`: Device ID.
+    DeviceId,
+}
+
+impl ReservedAddress {
+    /// All 7-bit addresses in this reserved range.
+    pub const fn addrs(&self) -> RangeInclusive<u8> {
+        match self {
+            Self::GeneralCall => 0x00..=0x00,
+            Self::Cbus => 0x01..=0x01,
+            Self::DifferentBusFormat => 0x02..=0x02,
+            Self::FuturePurposes => 0x03..=0x03,
+            Self::HsMasterCode => 0x04..=0x07,
+            Self::TenBitPrefix => 0x78..=0x7B,
+            Self::DeviceId => 0x7C..=0x7F,
+        }
+    }
+
+    /// First 7-bit address in this reserved range.
+    pub const fn addr(&self) -> u8 {
+        *self.addrs().start()
+    }
+}
+
+/// Classify a 7-bit address, returning the reserved range it belongs to.
+///
+/// Returns `None` if the address is not reserved, which includes addresses that are not valid
+/// 7-bit addresses (see [`is_target_addr`]).
+pub fn classify(addr: u8) -> Option<ReservedAddress> {
+    match addr {
+        0x00 => Some(ReservedAddress::GeneralCall),
+        0x01 => Some(ReservedAddress::Cbus),
+        0x02 => Some(ReservedAddress::DifferentBusFormat),
+        0x03 => Some(ReservedAddress::FuturePurposes),
+        0x04..=0x07 => Some(ReservedAddress::HsMasterCode),
+        0x78..=0x7B => Some(ReservedAddress::TenBitPrefix),
+        0x7C..=0x7F => Some(ReservedAddress::DeviceId),
+        _ => None,
+    }
+}
+
+/// Check if an address is a valid 7-bit address available to targets.
+pub fn is_target_addr(addr: u8) -> bool {
+    target_addrs().contains(&addr)
+}
+
+/// Iterate over all 7-bit addresses available to targets, in ascending order.
+pub fn target_addrs() -> RangeInclusive<u8> {
+    0x08..=0x77
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ReservedAddress; 7] = [
+        ReservedAddress::GeneralCall,
+        ReservedAddress::Cbus,
+        ReservedAddress::DifferentBusFormat,
+        ReservedAddress::FuturePurposes,
+        ReservedAddress::HsMasterCode,
+        ReservedAddress::TenBitPrefix,
+        ReservedAddress::DeviceId,
+    ];
+
+    #[test]
+    fn classify_matches_addrs() {
+        for addr in 0..=0x7F {
+            for r in ALL {
+                assert_eq!(
+                    classify(addr) == Some(r),
+                    r.addrs().contains(&addr),
+                    "{addr:#04x} {r:?}"
+                );
+            }
+            assert_eq!(
+                is_target_addr(addr),
+                classify(addr).is_none(),
+                "{addr:#04x}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_addrs() {
+        for addr in 0x80..=0xFF {
+            assert_eq!(classify(addr), None);
+            assert!(!is_target_addr(addr));
+        }
+    }
+}
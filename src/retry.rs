@@ -0,0 +1,238 @@
+//! Retrying general calls on transient bus errors.
+//!
+//! On a multi-master bus, arbitration loss and bus errors are expected from time to time, and a
+//! general call should simply be re-sent. A [`RetryPolicy`] decides which errors are retried, how
+//! many times and how long to back off between attempts.
+
+use embedded_hal::i2c::{self, ErrorKind, NoAcknowledgeSource};
+
+use crate::Error;
+
+/// Backoff strategy between attempts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Backoff {
+    /// Wait the same time, in microseconds, before every retry.
+    Fixed(u32),
+    /// Wait `initial_us` microseconds before the first retry, doubling before every subsequent
+    /// retry up to `max_us` microseconds.
+    Exponential {
+        /// Time before the first retry, in microseconds.
+        initial_us: u32,
+        /// Upper bound on the time between retries, in microseconds.
+        max_us: u32,
+    },
+}
+
+impl Backoff {
+    /// Time to wait, in microseconds, before the given retry (starting at 1 for the first retry).
+    pub fn delay_us(&self, retry: u8) -> u32 {
+        match *self {
+            Self::Fixed(us) => us,
+            Self::Exponential { initial_us, max_us } => {
+                let shift = u32::from(retry.saturating_sub(1)).min(31);
+                (u64::from(initial_us) << shift).min(u64::from(max_us)) as u32
+            }
+        }
+    }
+}
+
+/// Policy deciding if and how often a failed general call is retried.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one. Always at least one attempt is made.
+    pub max_attempts: u8,
+    /// Backoff strategy between attempts.
+    pub backoff: Backoff,
+    /// Decides whether an error of the given kind should be retried.
+    pub should_retry: fn(ErrorKind) -> bool,
+}
+
+impl RetryPolicy {
+    /// Check if an error should be retried under this policy.
+    pub fn is_retryable<E: i2c::Error>(&self, error: &Error<E>) -> bool {
+        let kind = match error {
+            Error::NoAckCall => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            Error::NoAckCmd | Error::NoAckData => {
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)
+            }
+            Error::NoAck => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            Error::I2C(e) => e.kind(),
+            // Invalid arguments will never succeed, and timeouts are not reported by the bus
+            Error::InvalidAddr | Error::InvalidCmd | Error::Timeout => return false,
+        };
+
+        (self.should_retry)(kind)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Backoff::Exponential {
+                initial_us: 100,
+                max_us: 10_000,
+            },
+            should_retry: retry_transient,
+        }
+    }
+}
+
+/// Default retry decision: retries arbitration loss and bus errors, which are transient on a
+/// multi-master bus.
+pub fn retry_transient(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::ArbitrationLoss | ErrorKind::Bus)
+}
+
+/// Error returned once a retried general call has given up.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RetryError<E> {
+    /// Error returned by the last attempt.
+    pub error: Error<E>,
+    /// Number of attempts made.
+    pub attempts: u8,
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch")
+)]
+mod asynch {
+    use core::num::NonZeroU8;
+
+    use embedded_hal_async::delay::DelayNs;
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{RetryError, RetryPolicy};
+    use crate::Command;
+    use crate::asynch::GeneralCall;
+
+    impl<I2C: I2c, D: DelayNs> GeneralCall<I2C, D> {
+        /// Issue a reset command general call, retrying according to `policy`.
+        ///
+        /// Returns the number of attempts made.
+        ///
+        /// # Errors
+        ///
+        /// If the last attempt fails or the error is not retryable, the error is returned along
+        /// with the number of attempts made. See [`Self::reset`] for the possible errors.
+        pub async fn reset_with_retry(
+            &mut self,
+            policy: &RetryPolicy,
+        ) -> Result<u8, RetryError<I2C::Error>> {
+            self.write_with_retry(Command::Reset.into(), policy).await
+        }
+
+        /// Issue an address latch command general call, retrying according to `policy`.
+        ///
+        /// Returns the number of attempts made.
+        ///
+        /// # Errors
+        ///
+        /// If the last attempt fails or the error is not retryable, the error is returned along
+        /// with the number of attempts made. See [`Self::latch_addr`] for the possible errors.
+        pub async fn latch_addr_with_retry(
+            &mut self,
+            policy: &RetryPolicy,
+        ) -> Result<u8, RetryError<I2C::Error>> {
+            self.write_with_retry(Command::LatchAddr.into(), policy)
+                .await
+        }
+
+        /// Issue an arbitrary command general call, retrying according to `policy`.
+        ///
+        /// Returns the number of attempts made.
+        ///
+        /// # Errors
+        ///
+        /// If the last attempt fails or the error is not retryable, the error is returned along
+        /// with the number of attempts made. See [`Self::call`] for the possible errors.
+        pub async fn call_with_retry(
+            &mut self,
+            cmd: NonZeroU8,
+            policy: &RetryPolicy,
+        ) -> Result<u8, RetryError<I2C::Error>> {
+            self.write_with_retry(cmd.get(), policy).await
+        }
+
+        async fn write_with_retry(
+            &mut self,
+            cmd: u8,
+            policy: &RetryPolicy,
+        ) -> Result<u8, RetryError<I2C::Error>> {
+            let mut attempts = 0;
+            loop {
+                if attempts > 0 {
+                    self.delay.delay_us(policy.backoff.delay_us(attempts)).await;
+                }
+                attempts += 1;
+
+                let res = self.write_cmd(cmd, &[]).await;
+                match res {
+                    Ok(()) => return Ok(attempts),
+                    Err(error) if attempts < policy.max_attempts && policy.is_retryable(&error) => {
+                    }
+                    Err(error) => {
+                        return self
+                            .resolve_nack(Err(error), self.pec)
+                            .await
+                            .map(|()| attempts)
+                            .map_err(|error| RetryError { error, attempts });
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_backoff() {
+        let backoff = Backoff::Fixed(250);
+        assert_eq!(backoff.delay_us(1), 250);
+        assert_eq!(backoff.delay_us(u8::MAX), 250);
+    }
+
+    #[test]
+    fn exponential_backoff_doubles_up_to_max() {
+        let backoff = Backoff::Exponential {
+            initial_us: 100,
+            max_us: 1_000,
+        };
+        assert_eq!(backoff.delay_us(0), 100);
+        assert_eq!(backoff.delay_us(1), 100);
+        assert_eq!(backoff.delay_us(2), 200);
+        assert_eq!(backoff.delay_us(4), 800);
+        assert_eq!(backoff.delay_us(5), 1_000);
+        assert_eq!(backoff.delay_us(u8::MAX), 1_000);
+    }
+
+    #[test]
+    fn exponential_backoff_saturates() {
+        let backoff = Backoff::Exponential {
+            initial_us: u32::MAX,
+            max_us: u32::MAX,
+        };
+        assert_eq!(backoff.delay_us(2), u32::MAX);
+        assert_eq!(backoff.delay_us(u8::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn default_policy_retries_transient_errors_only() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable(&Error::I2C(ErrorKind::ArbitrationLoss)));
+        assert!(policy.is_retryable(&Error::I2C(ErrorKind::Bus)));
+        assert!(!policy.is_retryable(&Error::I2C(ErrorKind::Overrun)));
+        assert!(!policy.is_retryable(&Error::<ErrorKind>::NoAckCall));
+        assert!(!policy.is_retryable(&Error::<ErrorKind>::Timeout));
+        assert!(!policy.is_retryable(&Error::<ErrorKind>::InvalidCmd));
+    }
+}
@@ -0,0 +1,105 @@
+//! Bus scanning.
+//!
+//! A scan probes every legal 7-bit target address (skipping the reserved ranges, see
+//! [`crate::reserved`]) and optionally reports how devices respond to general calls.
+
+use crate::AddressSet;
+
+/// How devices are probed for presence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ProbeStyle {
+    /// Zero-length write. Nothing is written to the device, but some devices misbehave when a
+    /// write is not followed by a register address.
+    #[default]
+    Write,
+    /// One-byte read. The read byte is discarded, but devices with read side effects (such as
+    /// clearing a status register) may be affected.
+    Read,
+}
+
+/// Configuration of a bus scan.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ScanConfig {
+    /// How devices are probed for presence.
+    pub probe: ProbeStyle,
+    /// Whether to probe the general call address (side-effect free).
+    pub general_call: bool,
+    /// Whether to report if the address latch and reset commands are acknowledged.
+    ///
+    /// **This actually issues both commands**, latching addresses and resetting devices that
+    /// honor them, as there is no way to test a command without sending it. The commands carry a
+    /// PEC byte if the driver has PEC enabled.
+    pub general_call_commands: bool,
+}
+
+/// Report of a bus scan.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ScanReport {
+    /// Target addresses which acknowledged a probe.
+    pub devices: AddressSet,
+    /// Whether any device acknowledged the general call address, if it was probed.
+    pub general_call: Option<bool>,
+    /// Whether any device acknowledged the address latch command, if it was probed.
+    pub latch_addr: Option<bool>,
+    /// Whether any device acknowledged the reset command, if it was probed.
+    pub reset: Option<bool>,
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch")
+)]
+mod asynch {
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{ProbeStyle, ScanConfig, ScanReport};
+    use crate::asynch::{GeneralCall, probe_addr};
+    use crate::reserved::target_addrs;
+    use crate::{Command, Error, ack_res_map};
+
+    impl<I2C: I2c, D> GeneralCall<I2C, D> {
+        /// Scan the bus for devices, skipping reserved addresses.
+        ///
+        /// # Errors
+        ///
+        /// If any I2C error other than a NACK occurs, the underlying error will be returned.
+        pub async fn scan(&mut self, config: &ScanConfig) -> Result<ScanReport, Error<I2C::Error>> {
+            let mut report = ScanReport::default();
+
+            for addr in target_addrs() {
+                let ack = match config.probe {
+                    ProbeStyle::Write => probe_addr(&mut self.i2c, addr).await?,
+                    ProbeStyle::Read => ack_res_map(self.i2c.read(addr, &mut [0]).await)?,
+                };
+                if ack {
+                    report.devices.insert(addr);
+                }
+            }
+
+            if config.general_call {
+                report.general_call = Some(self.probe().await?);
+            }
+
+            if config.general_call_commands {
+                for (cmd, ack) in [
+                    (Command::LatchAddr, &mut report.latch_addr),
+                    (Command::Reset, &mut report.reset),
+                ] {
+                    *ack = Some(match self.write_cmd(cmd.into(), &[]).await {
+                        Ok(()) => true,
+                        Err(Error::I2C(e)) => return Err(Error::I2C(e)),
+                        Err(_) => false,
+                    });
+                }
+            }
+
+            Ok(report)
+        }
+    }
+}
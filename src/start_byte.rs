@@ -0,0 +1,65 @@
+//! START byte transmission for targets that poll SDA in software.
+//!
+//! The START byte (`0000 0001`) gives slow targets, which sample the bus in software rather than
+//! with dedicated hardware, time to detect a START condition before a real transfer. No device is
+//! allowed to acknowledge it.
+
+// The START byte is the general call address with the R/W bit set
+const START_BYTE_ADDR: u8 = crate::GENERAL_CALL_ADDR;
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch")
+)]
+mod asynch {
+    use embedded_hal::i2c::Operation;
+    use embedded_hal_async::i2c::I2c;
+
+    use super::START_BYTE_ADDR;
+    use crate::asynch::GeneralCall;
+    use crate::{Error, nack_res_map};
+
+    impl<I2C: I2c, D> GeneralCall<I2C, D> {
+        /// Transmit a START byte.
+        ///
+        /// The START byte is expected to be NACKed, so a NACK is treated as success.
+        ///
+        /// # Errors
+        ///
+        /// If any I2C error other than a NACK occurs, the underlying error will be returned.
+        pub async fn start_byte(&mut self) -> Result<(), Error<I2C::Error>> {
+            // Zero-length reads are rejected by many HALs, and the NACK ends the read before any
+            // byte is clocked in anyway
+            let res = self.i2c.read(START_BYTE_ADDR, &mut [0]).await;
+            nack_res_map(res)
+        }
+
+        /// Transmit a START byte, followed by a directed transaction to the given 7-bit address.
+        ///
+        /// Note that `embedded-hal` cannot issue a repeated START to a different address, so the
+        /// START byte is terminated by a STOP and the transaction begins with a new START. This is
+        /// sufficient for targets that only need to detect the START byte to synchronize.
+        ///
+        /// # Errors
+        ///
+        /// If any I2C error other than a NACK occurs while transmitting the START byte, the
+        /// underlying error will be returned.
+        ///
+        /// If the directed transaction fails, its underlying error will be returned unchanged as
+        /// [`Error::I2C`], since it is not a general call.
+        pub async fn start_byte_transaction(
+            &mut self,
+            addr: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Error<I2C::Error>> {
+            self.start_byte().await?;
+            self.i2c
+                .transaction(addr, operations)
+                .await
+                .map_err(Error::I2C)
+        }
+    }
+}
@@ -0,0 +1,498 @@
+//! Target-side decoding of general calls.
+//!
+//! [`GeneralCallDecoder`] consumes the bytes a target receives after being addressed with the
+//! general call address, decides whether each byte should be acknowledged, and yields the
+//! [`Event`]s they encode. It does not touch any hardware, so it can sit behind any I2C target
+//! peripheral driver.
+//!
+//! [`ProgrammableAddr`] then reacts to those events like commercial parts with a partially
+//! programmable address do, re-reading the programmable bits from their address pins.
+
+use embedded_hal::digital::InputPin;
+
+use crate::Command;
+
+/// Event decoded from a general call received by a target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event<'a> {
+    /// Software reset command (0x06): reset and latch the programmable part of the address.
+    Reset,
+    /// Address latch command (0x04): latch the programmable part of the address without resetting.
+    LatchAddr,
+    /// Forbidden second byte (0x00), which the specification does not allow.
+    Forbidden,
+    /// Unknown software command.
+    Unknown(u8),
+    /// Data byte following an acknowledged unknown software command.
+    Data(u8),
+    /// Hardware general call, yielded once the transfer ends.
+    HardwareCall {
+        /// 7-bit address of the master announcing itself.
+        master_addr: u8,
+        /// Data bytes following the master address.
+        payload: &'a [u8],
+    },
+}
+
+/// Policy deciding which general call bytes a target acknowledges.
+///
+/// Bytes which are not acknowledged still yield their events, so a target may observe general
+/// calls without taking part in them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AckPolicy {
+    /// Acknowledge the software reset command.
+    pub reset: bool,
+    /// Acknowledge the address latch command.
+    pub latch_addr: bool,
+    /// Acknowledge unknown software commands, along with the data bytes following them.
+    pub unknown: bool,
+    /// Acknowledge hardware general calls, along with their payload as long as it fits.
+    pub hardware_call: bool,
+}
+
+impl Default for AckPolicy {
+    /// Acknowledge only the reset and address latch commands, as most devices do.
+    fn default() -> Self {
+        Self {
+            reset: true,
+            latch_addr: true,
+            unknown: false,
+            hardware_call: false,
+        }
+    }
+}
+
+// Position within a general call transfer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum State {
+    Idle,
+    Cmd,
+    UnknownData,
+    HardwareCall { master_addr: u8, ack: bool },
+    Ignore,
+}
+
+/// Decoder of general calls received by a target, buffering up to `N` bytes of hardware general
+/// call payload.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GeneralCallDecoder<const N: usize> {
+    policy: AckPolicy,
+    state: State,
+    payload: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> GeneralCallDecoder<N> {
+    /// Create a new decoder with the given acknowledgement policy.
+    pub const fn new(policy: AckPolicy) -> Self {
+        Self {
+            policy,
+            state: State::Idle,
+            payload: [0; N],
+            len: 0,
+        }
+    }
+
+    /// Get the acknowledgement policy.
+    pub fn policy(&self) -> &AckPolicy {
+        &self.policy
+    }
+
+    /// Set the acknowledgement policy. Takes effect from the next byte received.
+    pub fn set_policy(&mut self, policy: AckPolicy) {
+        self.policy = policy;
+    }
+
+    /// Signal that the target was addressed with the general call address, beginning a new
+    /// transfer.
+    pub fn start(&mut self) {
+        self.state = State::Cmd;
+        self.len = 0;
+    }
+
+    /// Feed a byte received during the transfer.
+    ///
+    /// Returns whether the byte should be acknowledged, along with the event it completes, if any.
+    /// Bytes received outside a transfer, or after a byte which was not acknowledged, are never
+    /// acknowledged.
+    pub fn receive(&mut self, byte: u8) -> (bool, Option<Event<'_>>) {
+        match self.state {
+            State::Idle | State::Ignore => (false, None),
+            State::Cmd => self.receive_cmd(byte),
+            State::UnknownData => (true, Some(Event::Data(byte))),
+            State::HardwareCall { ack: false, .. } => (false, None),
+            State::HardwareCall { ack: true, .. } => match self.payload.get_mut(self.len) {
+                Some(slot) => {
+                    *slot = byte;
+                    self.len += 1;
+                    (true, None)
+                }
+                None => (false, None),
+            },
+        }
+    }
+
+    // Decode the second byte of a general call
+    fn receive_cmd(&mut self, byte: u8) -> (bool, Option<Event<'_>>) {
+        let (ack, event, state) = if byte & 1 == 1 {
+            let ack = self.policy.hardware_call;
+            let master_addr = byte >> 1;
+            (ack, None, State::HardwareCall { master_addr, ack })
+        } else if byte == 0x00 {
+            (false, Some(Event::Forbidden), State::Ignore)
+        } else if byte == u8::from(Command::Reset) {
+            (self.policy.reset, Some(Event::Reset), State::Ignore)
+        } else if byte == u8::from(Command::LatchAddr) {
+            (
+                self.policy.latch_addr,
+                Some(Event::LatchAddr),
+                State::Ignore,
+            )
+        } else if self.policy.unknown {
+            (true, Some(Event::Unknown(byte)), State::UnknownData)
+        } else {
+            (false, Some(Event::Unknown(byte)), State::Ignore)
+        };
+
+        self.state = state;
+        (ack, event)
+    }
+
+    /// Signal the end of the transfer, on a STOP or repeated START condition.
+    ///
+    /// Returns the hardware general call received during the transfer, if any. Its payload is
+    /// truncated to the first `N` bytes, the remainder having not been acknowledged, and is empty
+    /// if the policy refuses hardware general calls.
+    pub fn end(&mut self) -> Option<Event<'_>> {
+        let state = core::mem::replace(&mut self.state, State::Idle);
+        match state {
+            State::HardwareCall { master_addr, .. } => Some(Event::HardwareCall {
+                master_addr,
+                payload: &self.payload[..self.len],
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl<const N: usize> Default for GeneralCallDecoder<N> {
+    fn default() -> Self {
+        Self::new(AckPolicy::default())
+    }
+}
+
+/// Address of a target whose `N` least significant bits are programmable through input pins, as
+/// latched on a reset or address latch general call.
+pub struct ProgrammableAddr<P, F, const N: usize> {
+    fixed: u8,
+    pins: [P; N],
+    on_reset: F,
+    addr: u8,
+}
+
+impl<P: InputPin, F: FnMut(), const N: usize> ProgrammableAddr<P, F, N> {
+    /// Create a new programmable address, latching its programmable bits immediately as a device
+    /// does at power-up.
+    ///
+    /// The 7-bit `fixed` address provides the fixed bits, its `N` least significant bits being
+    /// ignored. Pin `i` of `pins` drives bit `i` of the address, a high level setting it. Then
+    /// `on_reset` is called whenever a reset general call is handled, before re-latching the
+    /// address, so the target can reset its registers.
+    ///
+    /// # Errors
+    ///
+    /// If reading a pin fails, its error will be returned.
+    pub fn new(fixed: u8, pins: [P; N], on_reset: F) -> Result<Self, P::Error> {
+        const { assert!(N <= 7, "at most 7 address bits can be programmable") };
+
+        let mut addr = Self {
+            fixed: fixed & 0x7F & !((1 << N) - 1),
+            pins,
+            on_reset,
+            addr: 0,
+        };
+        addr.latch()?;
+        Ok(addr)
+    }
+
+    /// Destroy this instance and return the address pins and reset callback.
+    pub fn destroy(self) -> ([P; N], F) {
+        (self.pins, self.on_reset)
+    }
+
+    /// Get the currently latched 7-bit address.
+    pub fn addr(&self) -> u8 {
+        self.addr
+    }
+
+    /// Read the programmable bits from the pins and latch the resulting address, which is also
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// If reading a pin fails, its error will be returned and the previous address stays latched.
+    pub fn latch(&mut self) -> Result<u8, P::Error> {
+        let mut addr = self.fixed;
+        for (bit, pin) in self.pins.iter_mut().enumerate() {
+            if pin.is_high()? {
+                addr |= 1 << bit;
+            }
+        }
+
+        self.addr = addr;
+        Ok(addr)
+    }
+
+    /// Handle an event decoded from a general call, latching the address on [`Event::LatchAddr`]
+    /// and additionally calling the reset callback first on [`Event::Reset`]. Other events are
+    /// ignored.
+    ///
+    /// Only events whose byte was acknowledged should be handled, as devices refusing a command
+    /// do not act on it.
+    ///
+    /// # Errors
+    ///
+    /// If reading a pin fails, its error will be returned and the previous address stays latched.
+    pub fn handle(&mut self, event: &Event<'_>) -> Result<(), P::Error> {
+        match event {
+            Event::Reset => {
+                (self.on_reset)();
+                self.latch().map(|_| ())
+            }
+            Event::LatchAddr => self.latch().map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    use embedded_hal::digital::{ErrorKind, ErrorType};
+
+    use super::*;
+
+    // Address pin whose level is shared with the test, `None` making reads fail
+    struct Pin<'a>(&'a Cell<Option<bool>>);
+
+    impl ErrorType for Pin<'_> {
+        type Error = ErrorKind;
+    }
+
+    impl InputPin for Pin<'_> {
+        fn is_high(&mut self) -> Result<bool, ErrorKind> {
+            self.0.get().ok_or(ErrorKind::Other)
+        }
+
+        fn is_low(&mut self) -> Result<bool, ErrorKind> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    fn accept_all() -> AckPolicy {
+        AckPolicy {
+            reset: true,
+            latch_addr: true,
+            unknown: true,
+            hardware_call: true,
+        }
+    }
+
+    #[test]
+    fn idle_bytes_are_refused() {
+        let mut decoder = GeneralCallDecoder::<4>::default();
+        assert_eq!(decoder.receive(0x06), (false, None));
+        assert_eq!(decoder.end(), None);
+    }
+
+    #[test]
+    fn reset() {
+        let mut decoder = GeneralCallDecoder::<4>::default();
+        decoder.start();
+        assert_eq!(decoder.receive(0x06), (true, Some(Event::Reset)));
+        // Nothing may follow a reset
+        assert_eq!(decoder.receive(0x00), (false, None));
+        assert_eq!(decoder.end(), None);
+    }
+
+    #[test]
+    fn latch_addr() {
+        let mut decoder = GeneralCallDecoder::<4>::default();
+        decoder.start();
+        assert_eq!(decoder.receive(0x04), (true, Some(Event::LatchAddr)));
+        assert_eq!(decoder.end(), None);
+
+        // Refused commands are still reported
+        decoder.set_policy(AckPolicy {
+            latch_addr: false,
+            ..AckPolicy::default()
+        });
+        decoder.start();
+        assert_eq!(decoder.receive(0x04), (false, Some(Event::LatchAddr)));
+    }
+
+    #[test]
+    fn forbidden() {
+        let mut decoder = GeneralCallDecoder::<4>::new(accept_all());
+        decoder.start();
+        assert_eq!(decoder.receive(0x00), (false, Some(Event::Forbidden)));
+        assert_eq!(decoder.receive(0x06), (false, None));
+        assert_eq!(decoder.end(), None);
+    }
+
+    #[test]
+    fn unknown_refused_by_default() {
+        let mut decoder = GeneralCallDecoder::<4>::default();
+        decoder.start();
+        assert_eq!(decoder.receive(0x58), (false, Some(Event::Unknown(0x58))));
+        assert_eq!(decoder.receive(0x12), (false, None));
+    }
+
+    #[test]
+    fn unknown_with_data() {
+        let mut decoder = GeneralCallDecoder::<4>::new(accept_all());
+        decoder.start();
+        assert_eq!(decoder.receive(0x58), (true, Some(Event::Unknown(0x58))));
+        assert_eq!(decoder.receive(0x12), (true, Some(Event::Data(0x12))));
+        assert_eq!(decoder.receive(0x34), (true, Some(Event::Data(0x34))));
+        assert_eq!(decoder.end(), None);
+
+        // A new transfer starts from the command byte again
+        decoder.start();
+        assert_eq!(decoder.receive(0x06), (true, Some(Event::Reset)));
+    }
+
+    #[test]
+    fn hardware_call() {
+        let mut decoder = GeneralCallDecoder::<4>::new(accept_all());
+        decoder.start();
+        assert_eq!(decoder.receive((0x10 << 1) | 1), (true, None));
+        assert_eq!(decoder.receive(0xAB), (true, None));
+        assert_eq!(decoder.receive(0xCD), (true, None));
+        assert_eq!(
+            decoder.end(),
+            Some(Event::HardwareCall {
+                master_addr: 0x10,
+                payload: &[0xAB, 0xCD],
+            })
+        );
+        assert_eq!(decoder.end(), None);
+    }
+
+    #[test]
+    fn hardware_call_overflow() {
+        let mut decoder = GeneralCallDecoder::<2>::new(accept_all());
+        decoder.start();
+        assert_eq!(decoder.receive((0x10 << 1) | 1), (true, None));
+        assert_eq!(decoder.receive(0x01), (true, None));
+        assert_eq!(decoder.receive(0x02), (true, None));
+        assert_eq!(decoder.receive(0x03), (false, None));
+        assert_eq!(
+            decoder.end(),
+            Some(Event::HardwareCall {
+                master_addr: 0x10,
+                payload: &[0x01, 0x02],
+            })
+        );
+    }
+
+    #[test]
+    fn hardware_call_refused_by_default() {
+        let mut decoder = GeneralCallDecoder::<4>::default();
+        decoder.start();
+        assert_eq!(decoder.receive((0x10 << 1) | 1), (false, None));
+        assert_eq!(decoder.receive(0xAB), (false, None));
+        assert_eq!(
+            decoder.end(),
+            Some(Event::HardwareCall {
+                master_addr: 0x10,
+                payload: &[],
+            })
+        );
+    }
+
+    #[test]
+    fn fixed_bits_are_masked() {
+        let addr = ProgrammableAddr::new(0xFF, [] as [Pin; 0], || ()).unwrap();
+        assert_eq!(addr.addr(), 0x7F);
+
+        let low = Cell::new(Some(false));
+        let addr = ProgrammableAddr::new(0x4F, [Pin(&low), Pin(&low), Pin(&low)], || ()).unwrap();
+        assert_eq!(addr.addr(), 0x48);
+
+        let pins = [(); 7].map(|_| Pin(&low));
+        let addr = ProgrammableAddr::new(0x7F, pins, || ()).unwrap();
+        assert_eq!(addr.addr(), 0x00);
+    }
+
+    #[test]
+    fn pin_drives_matching_bit() {
+        let levels = [const { Cell::new(Some(false)) }; 3];
+        let mut addr = ProgrammableAddr::new(0x48, levels.each_ref().map(Pin), || ()).unwrap();
+        for (bit, level) in levels.iter().enumerate() {
+            level.set(Some(true));
+            assert_eq!(addr.latch(), Ok(0x48 | (1 << bit)));
+            level.set(Some(false));
+        }
+
+        levels[0].set(Some(true));
+        levels[2].set(Some(true));
+        assert_eq!(addr.latch(), Ok(0x4D));
+        assert_eq!(addr.addr(), 0x4D);
+    }
+
+    #[test]
+    fn reset_calls_on_reset_before_latching() {
+        let level = Cell::new(Some(false));
+        let resets = Cell::new(0);
+        // The reset raises the pin, which is only seen if latching happens afterwards
+        let on_reset = || {
+            resets.set(resets.get() + 1);
+            level.set(Some(true));
+        };
+        let mut addr = ProgrammableAddr::new(0x48, [Pin(&level)], on_reset).unwrap();
+        assert_eq!(resets.get(), 0);
+
+        addr.handle(&Event::Reset).unwrap();
+        assert_eq!(resets.get(), 1);
+        assert_eq!(addr.addr(), 0x49);
+    }
+
+    #[test]
+    fn latch_addr_does_not_reset() {
+        let level = Cell::new(Some(false));
+        let resets = Cell::new(0);
+        let on_reset = || resets.set(resets.get() + 1);
+        let mut addr = ProgrammableAddr::new(0x48, [Pin(&level)], on_reset).unwrap();
+
+        level.set(Some(true));
+        addr.handle(&Event::LatchAddr).unwrap();
+        assert_eq!(addr.addr(), 0x49);
+
+        level.set(Some(false));
+        addr.handle(&Event::Unknown(0x08)).unwrap();
+        assert_eq!(addr.addr(), 0x49);
+        assert_eq!(resets.get(), 0);
+    }
+
+    #[test]
+    fn pin_error_keeps_previous_addr() {
+        let levels = [Cell::new(Some(true)), Cell::new(Some(false))];
+        let mut addr = ProgrammableAddr::new(0x48, levels.each_ref().map(Pin), || ()).unwrap();
+        assert_eq!(addr.addr(), 0x49);
+
+        levels[0].set(Some(false));
+        levels[1].set(None);
+        assert_eq!(addr.latch(), Err(ErrorKind::Other));
+        assert_eq!(addr.handle(&Event::Reset), Err(ErrorKind::Other));
+        assert_eq!(addr.addr(), 0x49);
+
+        let broken = Cell::new(None);
+        assert!(ProgrammableAddr::new(0x48, [Pin(&broken)], || ()).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! Timeout-bounded async general calls.
+
+use core::future::{Future, poll_fn};
+use core::num::NonZeroU8;
+use core::pin::pin;
+use core::task::Poll;
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::asynch::{GeneralCall, resolve_unknown_nack, write_general_call};
+use crate::{Command, DEFAULT_TIMEOUT_US, Error};
+
+// Race a future against a timer, returning `None` if the timer expires first
+async fn with_timeout<D: DelayNs, F: Future>(
+    delay: &mut D,
+    timeout_us: u32,
+    fut: F,
+) -> Option<F::Output> {
+    let mut fut = pin!(fut);
+    let mut timer = pin!(delay.delay_us(timeout_us));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = fut.as_mut().poll(cx) {
+            Poll::Ready(Some(output))
+        } else if timer.as_mut().poll(cx).is_ready() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+impl<I2C: I2c, D: DelayNs> GeneralCall<I2C, D> {
+    // Write a single command byte general call, honoring PEC mode, within the timeout. Resolving
+    // an unknown NACK probes the bus again, so it shares the same timeout.
+    async fn write_with_timeout(
+        &mut self,
+        cmd: u8,
+        timeout_us: Option<u32>,
+    ) -> Result<(), Error<I2C::Error>> {
+        let timeout_us = timeout_us.unwrap_or(DEFAULT_TIMEOUT_US);
+        let (i2c, pec, resolve) = (&mut self.i2c, self.pec, self.resolve_unknown_nack);
+        let call = async move {
+            let res = write_general_call(i2c, pec, cmd, &[]).await;
+            if resolve {
+                resolve_unknown_nack(i2c, res, pec).await
+            } else {
+                res
+            }
+        };
+        with_timeout(&mut self.delay, timeout_us, call)
+            .await
+            .unwrap_or(Err(Error::Timeout))
+    }
+
+    /// Issue a reset command general call, giving up after `timeout_us` microseconds, or
+    /// [`DEFAULT_TIMEOUT_US`] (the SMBus clock low timeout) if `None`.
+    ///
+    /// # Errors
+    ///
+    /// If the general call, including the probe resolving an unknown NACK if
+    /// [`Self::set_resolve_unknown_nack`] is enabled, does not complete in time, [`Error::Timeout`]
+    /// will be returned. The pending I2C transaction is dropped and the driver remains usable,
+    /// although the state of the bus after cancellation depends on the HAL.
+    ///
+    /// Otherwise, same as [`Self::reset`].
+    pub async fn reset_with_timeout(
+        &mut self,
+        timeout_us: Option<u32>,
+    ) -> Result<(), Error<I2C::Error>> {
+        self.write_with_timeout(Command::Reset.into(), timeout_us)
+            .await
+    }
+
+    /// Issue an address latch command general call, giving up after `timeout_us` microseconds, or
+    /// [`DEFAULT_TIMEOUT_US`] (the SMBus clock low timeout) if `None`.
+    ///
+    /// # Errors
+    ///
+    /// If the general call, including the probe resolving an unknown NACK if
+    /// [`Self::set_resolve_unknown_nack`] is enabled, does not complete in time, [`Error::Timeout`]
+    /// will be returned. The pending I2C transaction is dropped and the driver remains usable,
+    /// although the state of the bus after cancellation depends on the HAL.
+    ///
+    /// Otherwise, same as [`Self::latch_addr`].
+    pub async fn latch_addr_with_timeout(
+        &mut self,
+        timeout_us: Option<u32>,
+    ) -> Result<(), Error<I2C::Error>> {
+        self.write_with_timeout(Command::LatchAddr.into(), timeout_us)
+            .await
+    }
+
+    /// Issue an arbitrary command general call, giving up after `timeout_us` microseconds, or
+    /// [`DEFAULT_TIMEOUT_US`] (the SMBus clock low timeout) if `None`.
+    ///
+    /// # Errors
+    ///
+    /// If the general call, including the probe resolving an unknown NACK if
+    /// [`Self::set_resolve_unknown_nack`] is enabled, does not complete in time, [`Error::Timeout`]
+    /// will be returned. The pending I2C transaction is dropped and the driver remains usable,
+    /// although the state of the bus after cancellation depends on the HAL.
+    ///
+    /// Otherwise, same as [`Self::call`].
+    pub async fn call_with_timeout(
+        &mut self,
+        cmd: NonZeroU8,
+        timeout_us: Option<u32>,
+    ) -> Result<(), Error<I2C::Error>> {
+        self.write_with_timeout(cmd.get(), timeout_us).await
+    }
+}
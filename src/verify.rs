@@ -0,0 +1,138 @@
+//! Verification of which devices respond after a general call reset.
+
+use crate::{AddressSet, Error};
+
+/// Configuration of how devices are polled when verifying their presence.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VerifyConfig {
+    /// Number of polling rounds performed before giving up on missing devices. Always at least one
+    /// round is performed.
+    pub attempts: u8,
+    /// Time, in microseconds, to wait between polling rounds.
+    pub retry_delay_us: u32,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            retry_delay_us: 1_000,
+        }
+    }
+}
+
+/// Report of which devices responded when verifying their presence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VerifyReport {
+    /// Expected addresses which acknowledged a probe.
+    pub present: AddressSet,
+    /// Expected addresses which never acknowledged a probe.
+    pub missing: AddressSet,
+    /// Addresses which acknowledged a probe but were not expected.
+    pub unexpected: AddressSet,
+}
+
+impl VerifyReport {
+    fn new<E>(expected: &[u8]) -> Result<Self, Error<E>> {
+        let mut missing = AddressSet::new();
+        for &addr in expected {
+            if !missing.insert(addr) {
+                return Err(Error::InvalidAddr);
+            }
+        }
+
+        Ok(Self {
+            missing,
+            ..Self::default()
+        })
+    }
+
+    /// Check if every expected device responded and no unexpected device did.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        idents(embedded_hal_async(sync = "embedded_hal"), asynch(sync = "blocking"))
+    ),
+    async(feature = "async", self = "asynch")
+)]
+mod asynch {
+    use embedded_hal_async::delay::DelayNs;
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{VerifyConfig, VerifyReport};
+    use crate::Error;
+    use crate::asynch::{GeneralCall, probe_addr};
+    use crate::reserved::target_addrs;
+
+    impl<I2C: I2c, D: DelayNs> GeneralCall<I2C, D> {
+        /// Poll each expected 7-bit address until it acknowledges or the retry budget in `config`
+        /// is exhausted, then probe the remaining legal target addresses for unexpected devices.
+        ///
+        /// Devices are probed with a zero-length write, so no data is written to them.
+        ///
+        /// # Errors
+        ///
+        /// If any expected address is not a valid 7-bit address, [`Error::InvalidAddr`] will be
+        /// returned and nothing is sent on the bus.
+        ///
+        /// If any I2C error other than a NACK occurs while probing, the underlying error will be
+        /// returned.
+        pub async fn verify_presence(
+            &mut self,
+            expected: &[u8],
+            config: &VerifyConfig,
+        ) -> Result<VerifyReport, Error<I2C::Error>> {
+            let mut report = VerifyReport::new(expected)?;
+            let expected = report.missing;
+
+            for attempt in 0..config.attempts.max(1) {
+                if attempt > 0 {
+                    self.delay.delay_us(config.retry_delay_us).await;
+                }
+
+                for addr in report.missing {
+                    if probe_addr(&mut self.i2c, addr).await? {
+                        report.missing.remove(addr);
+                        report.present.insert(addr);
+                    }
+                }
+
+                if report.missing.is_empty() {
+                    break;
+                }
+            }
+
+            for addr in target_addrs().filter(|&addr| !expected.contains(addr)) {
+                if probe_addr(&mut self.i2c, addr).await? {
+                    report.unexpected.insert(addr);
+                }
+            }
+
+            Ok(report)
+        }
+
+        /// Issue a reset command general call, wait the configured settle time, then verify which
+        /// of the expected devices came back (see [`Self::verify_presence`]).
+        ///
+        /// # Errors
+        ///
+        /// Same as [`Self::reset`] and [`Self::verify_presence`].
+        pub async fn reset_and_verify(
+            &mut self,
+            expected: &[u8],
+            config: &VerifyConfig,
+        ) -> Result<VerifyReport, Error<I2C::Error>> {
+            // Validate before resetting so a bad address list doesn't cause a spurious reset
+            VerifyReport::new::<I2C::Error>(expected)?;
+            self.reset_and_wait().await?;
+            self.verify_presence(expected, config).await
+        }
+    }
+}
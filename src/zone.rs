@@ -0,0 +1,463 @@
+//! PMBus 1.4 Zone Read and Zone Write.
+//!
+//! Each device is configured with ZONE_CONFIG to belong to a write zone and a read zone, and
+//! ZONE_ACTIVE selects which zones are active. Commands written to the Zone Write address are then
+//! executed by every device in the active write zone, and commands read from the Zone Read address
+//! are answered by every device in the active read zone.
+//!
+//! Every device in the read zone answers a Zone Read with its own address followed by its data.
+//! Since devices drive the bus simultaneously, the lowest address wins arbitration and the others
+//! answer again on the following reads, until every device has answered.
+
+use embedded_hal::i2c::Operation;
+
+use crate::pec::Pec;
+
+pub use blocking::ZoneController;
+
+/// PMBus Zone Write address.
+pub const ZONE_WRITE_ADDR: u8 = 0x37;
+
+/// PMBus Zone Read address.
+pub const ZONE_READ_ADDR: u8 = 0x28;
+
+// Zone commands
+const ZONE_CONFIG: u8 = 0x07;
+const ZONE_ACTIVE: u8 = 0x08;
+
+/// Zone error.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ZoneError<E> {
+    /// No device acknowledged the command.
+    NoAck,
+    /// The PEC byte received from a device did not match the data.
+    Pec,
+    /// More devices answered a Zone Read than fit in the provided response list.
+    TooManyDevices,
+    /// A device answered a Zone Read with the given address, which is not higher than the address
+    /// of the previous answer. Either a device answered twice or arbitration did not select
+    /// devices by ascending address.
+    OutOfOrder(u8),
+    /// The provided address is not a valid 7-bit address.
+    InvalidAddr,
+    /// Other I2C error was encountered.
+    I2C(E),
+}
+
+/// Write and read zones of a device, as set by ZONE_CONFIG or ZONE_ACTIVE.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Zones {
+    /// Zone whose Zone Writes are executed.
+    pub write: u8,
+    /// Zone whose Zone Reads are answered.
+    pub read: u8,
+}
+
+/// Answer of a single device to a Zone Read, carrying `N` data bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ZoneResponse<const N: usize> {
+    /// 7-bit address of the answering device.
+    pub addr: u8,
+    /// Data returned by the device.
+    pub data: [u8; N],
+}
+
+impl<const N: usize> Default for ZoneResponse<N> {
+    fn default() -> Self {
+        Self {
+            addr: 0,
+            data: [0; N],
+        }
+    }
+}
+
+impl<const N: usize> ZoneResponse<N> {
+    /// Parse the answer to a Zone Read of `cmd` from the address byte and data read from the bus,
+    /// verifying the trailing PEC byte if one was read.
+    ///
+    /// Returns `None` if the PEC does not match.
+    pub fn parse(cmd: u8, addr_byte: u8, data: &[u8; N], pec: Option<u8>) -> Option<Self> {
+        if let Some(pec) = pec {
+            let expected = Pec::new()
+                .update(&[
+                    ZONE_READ_ADDR << 1,
+                    cmd,
+                    (ZONE_READ_ADDR << 1) | 1,
+                    addr_byte,
+                ])
+                .update(data)
+                .value();
+            if pec != expected {
+                return None;
+            }
+        }
+
+        Some(Self {
+            addr: addr_byte >> 1,
+            data: *data,
+        })
+    }
+}
+
+// Pack the present operations at the front, since many HALs reject zero-length operations.
+// Returns the number of operations present.
+fn compact<'a, const M: usize>(ops: [Option<Operation<'a>>; M]) -> ([Operation<'a>; M], usize) {
+    let mut packed = [const { Operation::Write(&[]) }; M];
+    let mut len = 0;
+    for (slot, op) in packed.iter_mut().zip(ops.into_iter().flatten()) {
+        *slot = op;
+        len += 1;
+    }
+    (packed, len)
+}
+
+#[maybe_async_cfg::maybe(
+    sync(
+        self = "blocking",
+        "doc = \"Blocking PMBus zone controller.\"",
+        idents(embedded_hal_async(sync = "embedded_hal"))
+    ),
+    async(
+        feature = "async",
+        self = "asynch",
+        "doc = \"Async PMBus zone controller.\""
+    )
+)]
+pub mod asynch {
+    use core::slice;
+
+    use embedded_hal::i2c::{self, Operation};
+    use embedded_hal_async::i2c::I2c;
+
+    use super::{
+        ZONE_ACTIVE, ZONE_CONFIG, ZONE_READ_ADDR, ZONE_WRITE_ADDR, ZoneError, ZoneResponse, Zones,
+        compact,
+    };
+    use crate::pec::Pec;
+
+    fn res_map<E: i2c::Error>(res: Result<(), E>) -> Result<(), ZoneError<E>> {
+        res.map_err(|e| match e.kind() {
+            i2c::ErrorKind::NoAcknowledge(_) => ZoneError::NoAck,
+            _ => ZoneError::I2C(e),
+        })
+    }
+
+    /// PMBus zone controller.
+    pub struct ZoneController<I2C: I2c> {
+        i2c: I2C,
+        pec: bool,
+    }
+
+    impl<I2C: I2c> ZoneController<I2C> {
+        /// Create a new instance of a zone controller, without PEC.
+        pub fn new(i2c: I2C) -> Self {
+            Self { i2c, pec: false }
+        }
+
+        /// Destroy this controller instance and return the underlying I2C bus instance.
+        pub fn destroy(self) -> I2C {
+            self.i2c
+        }
+
+        /// Enable or disable Packet Error Checking on every zone transaction. Disabled by default.
+        pub fn set_pec(&mut self, enable: bool) {
+            self.pec = enable;
+        }
+
+        // Write a command and data to the given address, followed by PEC if enabled
+        async fn write(
+            &mut self,
+            addr: u8,
+            cmd: u8,
+            data: &[u8],
+        ) -> Result<(), ZoneError<I2C::Error>> {
+            let pec = [Pec::new().update(&[addr << 1, cmd]).update(data).value()];
+            let (mut ops, len) = compact([
+                Some(Operation::Write(slice::from_ref(&cmd))),
+                (!data.is_empty()).then_some(Operation::Write(data)),
+                self.pec.then_some(Operation::Write(&pec)),
+            ]);
+            let res = self.i2c.transaction(addr, &mut ops[..len]).await;
+            res_map(res)
+        }
+
+        /// Configure the write and read zones of the device at the given 7-bit address.
+        ///
+        /// # Errors
+        ///
+        /// If `addr` is not a valid 7-bit address, [`ZoneError::InvalidAddr`] will be returned.
+        ///
+        /// If the device does not acknowledge, [`ZoneError::NoAck`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn configure(
+            &mut self,
+            addr: u8,
+            zones: Zones,
+        ) -> Result<(), ZoneError<I2C::Error>> {
+            if addr > 0x7F {
+                return Err(ZoneError::InvalidAddr);
+            }
+            self.write(addr, ZONE_CONFIG, &[zones.write, zones.read])
+                .await
+        }
+
+        /// Select the active write and read zones of every zone-capable device, by writing
+        /// ZONE_ACTIVE to the Zone Write address.
+        ///
+        /// # Errors
+        ///
+        /// If no device acknowledges, [`ZoneError::NoAck`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn set_active(&mut self, zones: Zones) -> Result<(), ZoneError<I2C::Error>> {
+            self.write(ZONE_WRITE_ADDR, ZONE_ACTIVE, &[zones.write, zones.read])
+                .await
+        }
+
+        /// Issue a Zone Write, which every device in the active write zone executes.
+        ///
+        /// # Errors
+        ///
+        /// If no device acknowledges, [`ZoneError::NoAck`] will be returned.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn zone_write(
+            &mut self,
+            cmd: u8,
+            data: &[u8],
+        ) -> Result<(), ZoneError<I2C::Error>> {
+            self.write(ZONE_WRITE_ADDR, cmd, data).await
+        }
+
+        /// Issue Zone Reads of `cmd` until every device in the active read zone has answered,
+        /// storing each answer in `responses`.
+        ///
+        /// Answers are stored in the order devices won arbitration, i.e. by ascending address.
+        ///
+        /// Returns the number of devices which answered.
+        ///
+        /// # Errors
+        ///
+        /// If the PEC of an answer does not match, [`ZoneError::Pec`] will be returned.
+        ///
+        /// If more devices answer than fit in `responses`, [`ZoneError::TooManyDevices`] will be
+        /// returned.
+        ///
+        /// If an answer repeats an address or does not come in ascending address order,
+        /// [`ZoneError::OutOfOrder`] will be returned, rather than reading on indefinitely.
+        ///
+        /// In any of these cases, answers received so far remain stored in `responses`.
+        ///
+        /// If any other I2C error occurs, the underlying error will be returned.
+        pub async fn zone_read<const N: usize>(
+            &mut self,
+            cmd: u8,
+            responses: &mut [ZoneResponse<N>],
+        ) -> Result<usize, ZoneError<I2C::Error>> {
+            let mut count = 0;
+
+            loop {
+                let mut addr_byte = [0];
+                let mut data = [0; N];
+                let mut pec = [0];
+                let (mut ops, len) = compact([
+                    Some(Operation::Write(slice::from_ref(&cmd))),
+                    Some(Operation::Read(&mut addr_byte)),
+                    (N > 0).then_some(Operation::Read(&mut data)),
+                    self.pec.then_some(Operation::Read(&mut pec)),
+                ]);
+                let res = self.i2c.transaction(ZONE_READ_ADDR, &mut ops[..len]).await;
+
+                match res_map(res) {
+                    Ok(()) => (),
+                    Err(ZoneError::NoAck) => return Ok(count),
+                    Err(e) => return Err(e),
+                }
+
+                let pec = self.pec.then_some(pec[0]);
+                let response =
+                    ZoneResponse::parse(cmd, addr_byte[0], &data, pec).ok_or(ZoneError::Pec)?;
+
+                // Arbitration lets the lowest address through first, and devices only answer once
+                if let Some(prev) = count.checked_sub(1).and_then(|i| responses.get(i))
+                    && response.addr <= prev.addr
+                {
+                    return Err(ZoneError::OutOfOrder(response.addr));
+                }
+                *responses.get_mut(count).ok_or(ZoneError::TooManyDevices)? = response;
+                count += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, SevenBitAddress};
+
+    use super::*;
+
+    // Answers Zone Reads with the scripted addresses in turn, then NACKs. Zone Writes are recorded.
+    #[derive(Default)]
+    struct FakeBus<'a> {
+        answers: &'a [u8],
+        writes: usize,
+        ops: usize,
+    }
+
+    impl ErrorType for FakeBus<'_> {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for FakeBus<'_> {
+        fn transaction(
+            &mut self,
+            addr: SevenBitAddress,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), ErrorKind> {
+            self.ops = operations.len();
+            for op in operations.iter() {
+                let len = match op {
+                    Operation::Write(buf) => buf.len(),
+                    Operation::Read(buf) => buf.len(),
+                };
+                assert_ne!(len, 0, "zero-length operation");
+            }
+            if addr != ZONE_READ_ADDR {
+                self.writes += 1;
+                return Ok(());
+            }
+
+            let Some((&answer, rest)) = self.answers.split_first() else {
+                return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+            };
+            self.answers = rest;
+            for op in operations.iter_mut().skip(1) {
+                if let Operation::Read(buf) = op {
+                    buf.fill(answer);
+                }
+            }
+            if let Some(Operation::Read(addr_byte)) = operations.get_mut(1) {
+                addr_byte[0] = answer << 1;
+            }
+            Ok(())
+        }
+    }
+
+    fn read(
+        answers: &[u8],
+        responses: &mut [ZoneResponse<1>],
+    ) -> Result<usize, ZoneError<ErrorKind>> {
+        let mut zones = ZoneController::new(FakeBus {
+            answers,
+            ..Default::default()
+        });
+        zones.zone_read(0x8B, responses)
+    }
+
+    #[test]
+    fn zone_read_ascending() {
+        let mut responses = [ZoneResponse::default(); 4];
+        assert_eq!(read(&[0x40, 0x41, 0x45], &mut responses).unwrap(), 3);
+        assert_eq!(
+            responses[0],
+            ZoneResponse {
+                addr: 0x40,
+                data: [0x40]
+            }
+        );
+        assert_eq!(
+            responses[2],
+            ZoneResponse {
+                addr: 0x45,
+                data: [0x45]
+            }
+        );
+    }
+
+    #[test]
+    fn zone_read_stops_on_repeated_addr() {
+        let mut responses = [ZoneResponse::default(); 4];
+        assert!(matches!(
+            read(&[0x40, 0x41, 0x41, 0x42], &mut responses),
+            Err(ZoneError::OutOfOrder(0x41))
+        ));
+        assert_eq!(responses[0].addr, 0x40);
+        assert_eq!(responses[1].addr, 0x41);
+        assert_eq!(responses[2], ZoneResponse::default());
+    }
+
+    #[test]
+    fn zone_read_stops_on_descending_addr() {
+        let mut responses = [ZoneResponse::default(); 4];
+        assert!(matches!(
+            read(&[0x40, 0x42, 0x41], &mut responses),
+            Err(ZoneError::OutOfOrder(0x41))
+        ));
+        assert_eq!(responses[0].addr, 0x40);
+        assert_eq!(responses[1].addr, 0x42);
+    }
+
+    #[test]
+    fn zone_read_too_many_devices() {
+        let mut responses = [ZoneResponse::default(); 2];
+        assert!(matches!(
+            read(&[0x40, 0x41, 0x42], &mut responses),
+            Err(ZoneError::TooManyDevices)
+        ));
+        assert_eq!(responses[0].addr, 0x40);
+        assert_eq!(responses[1].addr, 0x41);
+    }
+
+    #[test]
+    fn zone_ops_without_pec() {
+        let mut bus = FakeBus {
+            answers: &[0x40],
+            ..Default::default()
+        };
+        ZoneController::new(&mut bus).zone_write(0x03, &[]).unwrap();
+        assert_eq!(bus.ops, 1);
+        ZoneController::new(&mut bus)
+            .set_active(Zones { write: 1, read: 1 })
+            .unwrap();
+        assert_eq!(bus.ops, 2);
+
+        let mut responses = [ZoneResponse::<0>::default(); 1];
+        let count = ZoneController::new(&mut bus)
+            .zone_read(0x8B, &mut responses)
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(bus.writes, 2);
+
+        let mut zones = ZoneController::new(&mut bus);
+        zones.set_pec(true);
+        zones.zone_write(0x03, &[]).unwrap();
+        assert_eq!(bus.ops, 2);
+    }
+
+    #[test]
+    fn parse_checks_pec() {
+        let data = [0x34, 0x12];
+        let pec = Pec::new()
+            .update(&[
+                ZONE_READ_ADDR << 1,
+                0x8B,
+                (ZONE_READ_ADDR << 1) | 1,
+                0x40 << 1,
+            ])
+            .update(&data)
+            .value();
+
+        let response = ZoneResponse::parse(0x8B, 0x40 << 1, &data, Some(pec)).unwrap();
+        assert_eq!(response, ZoneResponse { addr: 0x40, data });
+        assert_eq!(ZoneResponse::parse(0x8B, 0x41 << 1, &data, Some(pec)), None);
+        assert_eq!(
+            ZoneResponse::parse(0x8B, 0x41 << 1, &data, None).map(|r| r.addr),
+            Some(0x41)
+        );
+    }
+}